@@ -0,0 +1,304 @@
+use std::{env, fs, io::{self, Read}, process::ExitCode, time::{SystemTime, UNIX_EPOCH}};
+
+use nanoserde::{DeJson, SerJson};
+use takuzu_core::{board::{self, Board}, format::{self, JsonPuzzle}, import, pack::Pack, rules::{Rules, Mask}, print::{self, SheetPuzzle}, pdf};
+
+const USAGE: &str = "usage:
+  takuzu-cli generate [--size N|WxH] [--count K] [--seed S] [--mode serious|learn] [--plus] [--wrap] [--holes random|symmetric] [--threads T] [--json]
+  takuzu-cli solve <file> [--json]
+  takuzu-cli grade <file>
+  takuzu-cli validate <file>
+  takuzu-cli import <file> [--json]
+  takuzu-cli print <file> --out <sheet.pdf|sheet.svg> [--per-page N] [--no-answers]
+
+Generated puzzles only depend on the seed, --threads sets how many threads purge them (one per core by default).
+Puzzles are read as text, JSON, puzzle packs, Unruly game IDs or bare 0/1/. grids, \"-\" reads from stdin.";
+
+#[derive(PartialEq)]
+enum Mode {
+	Serious,
+	Learn,
+}
+
+struct Options {
+	width: usize,
+	height: usize,
+	count: usize,
+	seed: u64,
+	mode: Mode,
+	rules: Rules,
+	json: bool,
+	out: String,
+	per_page: usize,
+	answers: bool,
+	files: Vec<String>,
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+	let mut options = Options {
+		width: 8,
+		height: 8,
+		count: 1,
+		seed: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64,
+		mode: Mode::Serious,
+		rules: Rules::default(),
+		json: false,
+		out: String::new(),
+		per_page: 4,
+		answers: true,
+		files: Vec::new(),
+	};
+
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+		match arg.as_str() {
+			"--size" => {
+				let size = value()?;
+				let (w, h) = size.split_once('x').unwrap_or((size, size));
+				options.width = w.parse().map_err(|_| format!("\"{w}\" is not a valid width"))?;
+				options.height = h.parse().map_err(|_| format!("\"{h}\" is not a valid height"))?;
+				if options.width < 2 || options.height < 2 {
+					return Err(format!("a {}x{} board is too small, both sides need at least 2 cells", options.width, options.height));
+				}
+			}
+			"--count" => options.count = value()?.parse().map_err(|_| "--count needs a number")?,
+			"--seed" => options.seed = value()?.parse().map_err(|_| "--seed needs a number")?,
+			"--mode" => options.mode = match value()?.as_str() {
+				"serious" => Mode::Serious,
+				"learn" => Mode::Learn,
+				other => return Err(format!("unknown mode \"{other}\", expected \"serious\" or \"learn\"")),
+			},
+			"--plus" => options.rules.constraints = true,
+			"--wrap" => options.rules.wrap = true,
+			"--holes" => options.rules.mask = match value()?.as_str() {
+				"random" => Mask::Random,
+				"symmetric" => Mask::Symmetric,
+				other => return Err(format!("unknown hole pattern \"{other}\", expected \"random\" or \"symmetric\"")),
+			},
+			"--threads" => board::set_threads(value()?.parse().map_err(|_| "--threads needs a number")?),
+			"--json" => options.json = true,
+			"--out" => options.out = value()?.clone(),
+			"--per-page" => options.per_page = value()?.parse().map_err(|_| "--per-page needs a number")?,
+			"--no-answers" => options.answers = false,
+			_ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+			_ => options.files.push(arg.clone()),
+		}
+	}
+
+	Ok(options)
+}
+
+// every puzzle of a text, JSON or pack file, JSON ones may come with their solution
+fn read_puzzles(path: &str) -> Result<Vec<(Board, Option<Board>)>, String> {
+	let mut text = String::new();
+	if path == "-" {
+		io::stdin().read_to_string(&mut text).map_err(|e| format!("can't read stdin: {e}"))?;
+	}
+	else {
+		text = fs::read_to_string(path).map_err(|e| format!("can't read {path}: {e}"))?;
+	}
+
+	let trimmed = text.trim_start();
+	if trimmed.starts_with('[') {
+		let puzzles = Vec::<JsonPuzzle>::deserialize_json(&text).map_err(|e| format!("{path}: {e}"))?;
+		puzzles.iter().enumerate().map(|(i, p)| p.boards().map_err(|e| format!("{path}, puzzle {}: {e}", i + 1))).collect()
+	}
+	else if let Ok(pack) = Pack::deserialize_json(&text) {
+		let pack = pack.validate().map_err(|e| format!("{path}: {e}"))?;
+		Ok(pack.puzzles.into_iter().map(|p| (p.board, Some(p.solution))).collect())
+	}
+	else if trimmed.starts_with('{') {
+		let puzzle = JsonPuzzle::deserialize_json(&text).map_err(|e| format!("{path}: {e}"))?;
+		Ok(vec![puzzle.boards().map_err(|e| format!("{path}: {e}"))?])
+	}
+	else if text.lines().find(|line| !line.trim().is_empty()).is_some_and(|line| format::parse_header(line).is_ok()) {
+		Ok(format::from_text(&text).map_err(|e| format!("{path}: {e}"))?.into_iter().map(|board| (board, None)).collect())
+	}
+	else {
+		Ok(import::import(&text).map_err(|e| format!("{path}: {e}"))?.into_iter().map(|board| (board, None)).collect())
+	}
+}
+
+fn file(options: &Options) -> Result<&str, String> {
+	match options.files.as_slice() {
+		[file] => Ok(file),
+		[] => Err("missing the puzzle file".to_string()),
+		_ => Err("expected a single puzzle file".to_string()),
+	}
+}
+
+fn generate(options: &Options) -> Result<bool, String> {
+	if !Board::is_feasible(options.width, options.height, options.rules) {
+		return Err(format!("no {}x{} board with unique rows and columns exists", options.width, options.height));
+	}
+	quad_rand::srand(options.seed);
+
+	let mut puzzles = Vec::new();
+	for _ in 0..options.count {
+		let puzzle = match options.mode {
+			Mode::Serious => Board::new_serious(options.width, options.height, options.rules, 0),
+			Mode::Learn => Board::new_learn(options.width, options.height, options.rules, 0),
+		};
+		if options.json {
+			puzzles.push(JsonPuzzle::new(&puzzle, puzzle.unique_solution().as_ref(), puzzle.grade()));
+		}
+		else {
+			println!("{}", format::to_text(&puzzle));
+		}
+	}
+
+	if options.json {
+		println!("{}", puzzles.serialize_json());
+	}
+	Ok(true)
+}
+
+fn solve(options: &Options) -> Result<bool, String> {
+	let mut all_solved = true;
+	let mut solutions = Vec::new();
+	for (i, (puzzle, _)) in read_puzzles(file(options)?)?.iter().enumerate() {
+		match puzzle.solutions(2).as_slice() {
+			[solution] => {
+				if options.json {
+					solutions.push(JsonPuzzle::new(puzzle, Some(solution), puzzle.grade()));
+				}
+				else {
+					println!("{}", format::to_text(solution));
+				}
+			}
+			found => {
+				eprintln!("puzzle {}: {}", i + 1, if found.is_empty() {"has no solution"} else {"has more than one solution"});
+				all_solved = false;
+			}
+		}
+	}
+
+	if options.json {
+		println!("{}", solutions.serialize_json());
+	}
+	Ok(all_solved)
+}
+
+fn grade(options: &Options) -> Result<bool, String> {
+	let mut all_graded = true;
+	for (i, (puzzle, _)) in read_puzzles(file(options)?)?.iter().enumerate() {
+		match puzzle.grade() {
+			Some(difficulty) => println!("puzzle {}: {}x{} {}", i + 1, puzzle.width, puzzle.height, difficulty.as_str()),
+			None => {
+				println!("puzzle {}: {}x{} ungradable, it doesn't have exactly one solution", i + 1, puzzle.width, puzzle.height);
+				all_graded = false;
+			}
+		}
+	}
+	Ok(all_graded)
+}
+
+fn validate(options: &Options) -> Result<bool, String> {
+	let mut all_valid = true;
+	for (i, (puzzle, solution)) in read_puzzles(file(options)?)?.iter().enumerate() {
+		let problem = if let Some(violation) = puzzle.errors.first() {
+			Some(format!("the givens break the {} rule", format!("{:?}", violation.rule).to_lowercase()))
+		}
+		else {
+			match puzzle.solutions(2).as_slice() {
+				[] => Some("no solution".to_string()),
+				[found] => match solution {
+					Some(solution) if solution.map != found.map => Some("the included solution is wrong".to_string()),
+					_ => None,
+				},
+				_ => Some("more than one solution".to_string()),
+			}
+		};
+
+		match problem {
+			Some(problem) => {
+				println!("puzzle {}: invalid, {problem}", i + 1);
+				all_valid = false;
+			}
+			None => println!("puzzle {}: valid", i + 1),
+		}
+	}
+	Ok(all_valid)
+}
+
+// puzzles of any readable format in the text format, or JSON with their solution
+fn convert(options: &Options) -> Result<bool, String> {
+	let puzzles = read_puzzles(file(options)?)?;
+	if options.json {
+		let puzzles: Vec<JsonPuzzle> = puzzles.iter().map(|(puzzle, solution)| {
+			let solution = solution.clone().or_else(|| puzzle.unique_solution());
+			JsonPuzzle::new(puzzle, solution.as_ref(), puzzle.grade())
+		}).collect();
+		println!("{}", puzzles.serialize_json());
+	}
+	else {
+		for (puzzle, _) in puzzles {
+			println!("{}", format::to_text(&puzzle));
+		}
+	}
+	Ok(true)
+}
+
+// a PDF with every page, or one SVG per page numbered after the first
+fn print(options: &Options) -> Result<bool, String> {
+	if options.out.is_empty() {
+		return Err("missing --out, the sheet to write".to_string());
+	}
+	if !(1..=12).contains(&options.per_page) {
+		return Err("--per-page has to be between 1 and 12".to_string());
+	}
+
+	let mut puzzles = Vec::new();
+	for (i, (givens, solution)) in read_puzzles(file(options)?)?.into_iter().enumerate() {
+		let solution = solution.or_else(|| givens.unique_solution());
+		if solution.is_none() && options.answers {
+			eprintln!("puzzle {}: doesn't have exactly one solution, it's left out of the answers", i + 1);
+		}
+		let label = format!("{}. {}x{}", i + 1, givens.width, givens.height);
+		puzzles.push(SheetPuzzle { label, givens, solution });
+	}
+	let pages = print::layout("Takuzu", &puzzles, options.per_page, options.answers);
+
+	let write = |path: &str, contents: &[u8]| fs::write(path, contents).map_err(|e| format!("can't write {path}: {e}"));
+	if let Some(stem) = options.out.strip_suffix(".svg") {
+		for (i, page) in pages.iter().enumerate() {
+			let path = if i == 0 {options.out.clone()} else {format!("{stem}-{}.svg", i + 1)};
+			write(&path, print::svg(page).as_bytes())?;
+		}
+	}
+	else if options.out.ends_with(".pdf") {
+		write(&options.out, &pdf::pdf(&pages))?;
+	}
+	else {
+		return Err(format!("can't tell the format of {}, expected a .pdf or .svg file", options.out));
+	}
+	Ok(true)
+}
+
+fn main() -> ExitCode {
+	let args: Vec<String> = env::args().skip(1).collect();
+	let Some(command) = args.first() else {
+		eprintln!("{USAGE}");
+		return ExitCode::FAILURE;
+	};
+
+	let result = parse_options(&args[1..]).and_then(|options| match command.as_str() {
+		"generate" => generate(&options),
+		"solve" => solve(&options),
+		"grade" => grade(&options),
+		"validate" => validate(&options),
+		"import" => convert(&options),
+		"print" => print(&options),
+		_ => Err(format!("unknown command \"{command}\"\n\n{USAGE}")),
+	});
+
+	match result {
+		Ok(true) => ExitCode::SUCCESS,
+		Ok(false) => ExitCode::FAILURE,
+		Err(e) => {
+			eprintln!("error: {e}");
+			ExitCode::FAILURE
+		}
+	}
+}
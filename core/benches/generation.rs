@@ -0,0 +1,61 @@
+// cargo bench -p takuzu-core
+// times purging and finding a solution on one thread and on all of them, for a few sizes
+
+use std::{hint::black_box, thread, time::{Duration, Instant}};
+
+use takuzu_core::{board::{self, Board}, rules::Rules};
+
+const SIZES: [usize; 4] = [8, 12, 16, 20];
+const RUNS: u64 = 3;
+
+// the average of the runs, every run starting from the same seed
+fn time<F: FnMut()>(seed: u64, mut f: F) -> Duration {
+	let mut total = Duration::ZERO;
+	for run in 0..RUNS {
+		quad_rand::srand(seed + run);
+		let start = Instant::now();
+		f();
+		total += start.elapsed();
+	}
+	total / RUNS as u32
+}
+
+fn row(name: &str, size: usize, serial: Duration, parallel: Duration) {
+	let speedup = serial.as_secs_f64() / parallel.as_secs_f64().max(f64::EPSILON);
+	println!("{name:<8} {size:>2}x{size:<2} {:>10.2?} {:>10.2?} {speedup:>7.2}x", serial, parallel);
+}
+
+fn main() {
+	let cores = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+	println!("{cores} threads\n");
+	println!("{:<8} {:<5} {:>10} {:>10} {:>8}", "", "size", "1 thread", "all", "speedup");
+
+	for size in SIZES {
+		// purging the same solution, the cells checked are split between threads
+		let mut solved = Board::new(size, size, Rules::default(), 0, true);
+		quad_rand::srand(size as u64);
+		solved.generate_solution();
+		let purge = |threads| {
+			board::set_threads(threads);
+			time(size as u64, || {
+				let mut board = solved.clone();
+				board.purge_redundancies();
+				black_box(board);
+			})
+		};
+		let (serial, parallel) = (purge(1), purge(cores));
+		row("purge", size, serial, parallel);
+
+		// one attempt after another against attempts racing on every thread
+		board::set_threads(cores);
+		let serial = time(size as u64, || {
+			let mut board = Board::new(size, size, Rules::default(), 0, true);
+			black_box(board.generate_valid());
+		});
+		let racing = time(size as u64, || {
+			let mut board = Board::new(size, size, Rules::default(), 0, true);
+			black_box(board.generate_valid_racing(&mut |_, _| true));
+		});
+		row("solution", size, serial, racing);
+	}
+}
@@ -0,0 +1,1152 @@
+use std::{sync::{Mutex, atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering}}, thread};
+
+use quad_rand as rand;
+use crate::{cell_state::CellState, constraint::Constraint, rules::{Rules, Mask}};
+
+// threads checking cells at once while purging, 0 for one per core
+static THREADS: AtomicUsize = AtomicUsize::new(0);
+// fewer filled cells than this are checked on the calling thread, starting threads would take longer
+const PARALLEL_CELLS: usize = 64;
+// boards up to this many cells are solved once to tell if they can be generated, counting lines isn't enough for them
+const SEARCHED_CELLS: usize = 100;
+// width, height and wrap of the searched boards, and whether they had a solution
+static SEARCHED: Mutex<Vec<(usize, usize, bool, bool)>> = Mutex::new(Vec::new());
+
+pub fn set_threads(threads: usize) {
+	THREADS.store(threads, Ordering::Relaxed);
+}
+
+pub fn threads() -> usize {
+	match THREADS.load(Ordering::Relaxed) {
+		0 => thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+		n => n,
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Rule {
+	Triplet,
+	Balance,
+	Duplicate,
+	Constraint,
+}
+
+// the steps of generating a puzzle, reported with the fraction of it done
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Phase {
+	Filling, // a solution
+	Purging, // the cells the puzzle can do without
+}
+
+impl Phase {
+	pub fn as_str(&self) -> &str {
+		match self {
+			Phase::Filling => "filling",
+			Phase::Purging => "purging",
+		}
+	}
+}
+
+// one deduction on the board, true if it filled in a cell
+pub type Deduction = Box<dyn Fn(&mut Board) -> bool>;
+
+#[derive(Clone, Debug)]
+pub struct Violation {
+	pub rule: Rule,
+	pub regions: Vec<(usize, usize, usize, usize)>, // x, y, w, h
+}
+
+#[derive(Clone)]
+pub struct Board {
+	pub id: usize, // for determining if a board returned by the generator thred regards this exact board
+	pub is_won: bool,
+	pub is_valid: bool,
+	pub is_generating: bool,
+	pub generation_end_time: f32,
+	pub generation_duration: f32,
+	pub width: usize,
+	pub height: usize,
+	pub rules: Rules,
+	pub map: Vec<Vec<CellState>>,
+	pub constraints: Vec<Vec<[Constraint; 2]>>, // with the right neighbour, with the neighbour below
+	pub errors: Vec<Violation>, // every rule violation on the board
+	pub error_time: f32, // this and the other timestamps are kept by the front end, the core never reads the clock
+	pub hint: Option<(usize, usize)>,
+	pub show_locked: Option<f32>,
+	pub last_error_sound: f32,
+	pub progress: Option<(Phase, f32)>, // of the generation, while it runs on another thread
+}
+
+impl Board {
+	pub fn new(width: usize, height: usize, mut rules: Rules, id: usize, will_generate: bool) -> Self {
+		// an even board plays the same no matter the setting, keep them under one highscore
+		rules.odd_sizes = width % 2 == 1 || height % 2 == 1;
+
+		Self {
+			is_won: false,
+			is_valid: true,
+			is_generating: will_generate,
+			width,
+			height,
+			rules,
+			map: vec![vec![CellState::None; width]; height],
+			constraints: vec![vec![[Constraint::None; 2]; width]; height],
+			errors: Vec::new(),
+			error_time: 0.0,
+			hint: None,
+			show_locked: None,
+			last_error_sound: -1.0,
+			id,
+			generation_end_time: -1.0,
+			generation_duration: -1.0,
+			progress: None,
+		}
+	}
+
+	pub fn new_learn(width: usize, height: usize, rules: Rules, id: usize) -> Self {
+		Self::new_puzzle(width, height, rules, id, true, false, &mut |_, _| true).unwrap()
+	}
+
+	pub fn new_serious(width: usize, height: usize, rules: Rules, id: usize) -> Self {
+		Self::new_puzzle(width, height, rules, id, false, false, &mut |_, _| true).unwrap()
+	}
+
+	// learn puzzles keep the cells the simple rules give away. Racing is faster, but the puzzle can't be reproduced from the seed.
+	// Progress goes to report, which stops the generation by returning false
+	pub fn new_puzzle(width: usize, height: usize, rules: Rules, id: usize, learn: bool, race: bool, report: &mut dyn FnMut(Phase, f32) -> bool) -> Option<Self> {
+		let mut board = Board::new(width, height, rules, id, true);
+		let solved = if race && rules.mask == Mask::None {board.generate_valid_racing(report).is_some()} else {board.generate_solution_with(report)};
+		if !solved {
+			return None;
+		}
+		if rules.constraints {
+			board.place_constraints(0.3);
+		}
+		if learn {
+			board.degenerate();
+		}
+		if !board.purge_redundancies_with(report) {
+			return None;
+		}
+		if rules.constraints {
+			board.purge_constraints();
+		}
+		board.lock_tiles();
+		Some(board)
+	}
+
+	// the placeholder shown while generating takes the generated puzzle over, generated boards still count as generating
+	pub fn fill_generated(&mut self, generated: Board, now: f32, duration: f32) {
+		self.map = generated.map;
+		self.constraints = generated.constraints;
+		self.is_generating = false;
+		self.generation_end_time = now;
+		self.generation_duration = duration;
+	}
+
+	// a partially filled board, shown while picking the size and variant
+	pub fn new_preview(width: usize, height: usize, rules: Rules) -> Self {
+		let mut board = Board::new(width, height, rules, 0, false);
+		board.place_mask(0.12);
+		board.generate_fraction(0.6);
+		if rules.constraints {
+			board.place_constraints(0.3);
+		}
+		board
+	}
+
+	// most cells of one color a line can hold. Odd lines may have one more of a color than the other, even ones are split equally
+	pub fn balance_limit(len: usize) -> usize {
+		len.div_ceil(2)
+	}
+
+	// number of distinct lines of the given length satisfying the triplet and balance rules
+	pub fn max_lines(len: usize, wrap: bool) -> usize {
+		Self::lines(len, wrap).len()
+	}
+
+	// every line of the given length satisfying the triplet and balance rules
+	pub fn lines(len: usize, wrap: bool) -> Vec<Vec<bool>> {
+		fn collect(line: &mut Vec<bool>, len: usize, wrap: bool, lines: &mut Vec<Vec<bool>>) {
+			let n = line.len();
+			if n == len {
+				let wrapped_triplet = wrap && len >= 3 && (len - 2..len).any(|i| line[i] == line[(i + 1) % len] && line[i] == line[(i + 2) % len]);
+				if !wrapped_triplet {
+					lines.push(line.clone());
+				}
+				return;
+			}
+
+			for cell in [true, false] {
+				let same = line.iter().filter(|c| **c == cell).count() + 1;
+				let triplet = n >= 2 && line[n - 1] == cell && line[n - 2] == cell;
+				if !triplet && same <= Board::balance_limit(len) {
+					line.push(cell);
+					collect(line, len, wrap, lines);
+					line.pop();
+				}
+			}
+		}
+
+		let mut lines = Vec::new();
+		collect(&mut Vec::new(), len, wrap, &mut lines);
+		lines
+	}
+
+	// lines have to be unique, so each dimension is capped by how many different lines fit along the other one.
+	// Small boards can pass that and still have no solution, 4x5 with wrap-around does, so they are solved once as well
+	pub fn is_feasible(width: usize, height: usize, rules: Rules) -> bool {
+		if width > Self::max_lines(height, rules.wrap) || height > Self::max_lines(width, rules.wrap) {
+			return false;
+		}
+		if width * height > SEARCHED_CELLS {
+			return true;
+		}
+		let mut searched = SEARCHED.lock().unwrap();
+		if let Some(&(.., solvable)) = searched.iter().find(|(w, h, wrap, _)| (*w, *h, *wrap) == (width, height, rules.wrap)) {
+			return solvable;
+		}
+		// the board is built from its shorter lines, there are fewer of them to keep apart than the longer ones
+		let (short, long) = (width.min(height), width.max(height));
+		let solvable = Self::has_solution(&Self::lines(short, rules.wrap), long, rules.wrap, &mut Vec::new());
+		searched.push((width, height, rules.wrap, solvable));
+		solvable
+	}
+
+	// stacks distinct rows until there are `height` of them, every column has to keep following the rules on the way
+	fn has_solution(rows: &[Vec<bool>], height: usize, wrap: bool, stacked: &mut Vec<usize>) -> bool {
+		let column = |x: usize, stacked: &[usize]| stacked.iter().map(|r| rows[*r][x]).collect::<Vec<bool>>();
+		if stacked.len() == height {
+			let columns: Vec<Vec<bool>> = (0..rows[0].len()).map(|x| column(x, stacked)).collect();
+			let wrapped_triplet = |c: &Vec<bool>| wrap && height >= 3 && (height - 2..height).any(|i| c[i] == c[(i + 1) % height] && c[i] == c[(i + 2) % height]);
+			return columns.iter().enumerate().all(|(i, c)| !wrapped_triplet(c) && !columns[..i].contains(c));
+		}
+
+		for r in 0..rows.len() {
+			if stacked.contains(&r) {
+				continue;
+			}
+			stacked.push(r);
+			let n = stacked.len();
+			let fits = (0..rows[r].len()).all(|x| {
+				let c = column(x, stacked);
+				let triplet = n >= 3 && c[n - 1] == c[n - 2] && c[n - 1] == c[n - 3];
+				!triplet && c.iter().filter(|b| **b).count().max(c.iter().filter(|b| !**b).count()) <= Self::balance_limit(height)
+			});
+			if fits && Self::has_solution(rows, height, wrap, stacked) {
+				return true;
+			}
+			stacked.pop();
+		}
+		false
+	}
+
+	// whether the triplet rule of a line continues over its ends
+	pub fn wraps(&self, len: usize) -> bool {
+		self.rules.wrap && len >= 3
+	}
+
+	// index of the cell at the given offset along a line, on wrapping boards it comes back from the other end
+	pub fn neighbour(&self, c2: usize, offset: i32, len: usize) -> Option<usize> {
+		let c = c2 as i32 + offset;
+		if self.wraps(len) {
+			Some(c.rem_euclid(len as i32) as usize)
+		}
+		else if c >= 0 && c < len as i32 {
+			Some(c as usize)
+		}
+		else {
+			None
+		}
+	}
+
+	pub fn has_nones(&self) -> bool {
+		for row in &self.map {
+			for cell in row {
+				if *cell == CellState::None {
+					return true;
+				}
+			}
+		}
+		false
+	}
+
+	pub fn count_nones(&self) -> u32 {
+		let mut counter = 0;
+		for row in &self.map {
+			for cell in row {
+				if *cell == CellState::None {
+					counter += 1;
+				}
+			}
+		}
+		counter
+	}
+
+	// points out a cell that can be deduced in one step, returns whether one was found
+	pub fn pick_hint(&mut self) -> bool {
+
+		let mut vec = Vec::new();
+		let mut clone = self.clone();
+		
+		let mut actions: Vec<Deduction> = vec![
+			Box::new(|board| {board.surround_doubles_axis(board.height, board.width, |v, y, x| v[y][x], |v, y, x, s| v[y][x] = s)}),
+			Box::new(|board| {board.surround_doubles_axis(board.width, board.height, |v, x, y| v[y][x], |v, x, y, s| v[y][x] = s)}),
+			Box::new(|board| {board.separate_triples_axis(board.height, board.width, |v, y, x| v[y][x], |v, y, x, s| v[y][x] = s)}),
+			Box::new(|board| {board.separate_triples_axis(board.width, board.height, |v, x, y| v[y][x], |v, x, y, s| v[y][x] = s)}),
+			Box::new(|board| {board.fill_rows_axis(		  board.height, board.width, |v, y, x| v[y][x], |v, y, x, s| v[y][x] = s)}),
+			Box::new(|board| {board.fill_rows_axis(		  board.width, board.height, |v, x, y| v[y][x], |v, x, y, s| v[y][x] = s)}),
+			Box::new(|board| {board.apply_constraints_axis(board.height, board.width, |v, y, x| v[y][x], |v, y, x, s| v[y][x] = s, |c, y, x| c[y][x][0])}),
+			Box::new(|board| {board.apply_constraints_axis(board.width, board.height, |v, x, y| v[y][x], |v, x, y, s| v[y][x] = s, |c, x, y| c[y][x][1])}),
+			Box::new(|board| {board.extend_equal_pairs_axis(board.height, board.width, |v, y, x| v[y][x], |v, y, x, s| v[y][x] = s, |c, y, x| c[y][x][0])}),
+			Box::new(|board| {board.extend_equal_pairs_axis(board.width, board.height, |v, x, y| v[y][x], |v, x, y, s| v[y][x] = s, |c, x, y| c[y][x][1])}),
+		];
+
+		use rand::ChooseRandom;
+		actions.shuffle();
+
+		let mut completed = false;
+		for action in actions.iter() {
+			if action(&mut clone) {
+				completed = true;
+				break;
+			}
+		}
+
+		if completed {
+			for y in 0..self.height {
+				for x in 0..self.width {
+					if self.map[y][x] == CellState::None && clone.map[y][x] != CellState::None {
+						vec.push((x, y));
+					}
+				}
+			}
+		}
+		
+
+		if !vec.is_empty() {
+			self.hint = Some(*vec.choose().unwrap());
+		}
+		self.hint.is_some()
+	}
+
+	pub fn lock_tiles(&mut self) {
+		for y in 0..self.height {
+			for x in 0..self.width {
+				self.map[y][x] = match self.map[y][x] {
+					CellState::False(false) => CellState::False(true),
+					CellState::True(false) => CellState::True(true),
+					rest => rest
+				}
+			}
+		}
+	}
+
+	pub fn verify_board(&mut self) {
+		let mut errors = self.get_errors_axis(self.width, self.height, |v, x, y| v[y][x]);
+		for violation in errors.iter_mut() {
+			for region in violation.regions.iter_mut() {
+				*region = (region.1, region.0, region.3, region.2);
+			}
+		}
+		errors.append(&mut self.get_errors_axis(self.height, self.width, |v, y, x| v[y][x]));
+		errors.append(&mut self.get_constraint_errors());
+
+		self.is_valid = errors.is_empty();
+		self.errors = errors;
+
+		self.is_won = !self.has_nones() && self.is_valid;
+	}
+
+	// returns every violation found along one axis, in that axis' coordinates
+	pub fn get_errors_axis<F : Fn(&Vec<Vec<CellState>>, usize, usize) -> CellState>(&self, lines: usize, len: usize, get: F) -> Vec<Violation> { 
+		let mut errors = Vec::new();
+
+		for c1 in 0..lines {
+			// runs of equal cells as (start, length, state)
+			let mut runs: Vec<(usize, usize, CellState)> = Vec::new();
+			for c2 in 0..len {
+				let state = get(&self.map, c1, c2);
+				match runs.last_mut() {
+					Some(run) if run.2 == state => run.1 += 1,
+					_ => runs.push((c2, 1, state)),
+				}
+			}
+
+			// on wrapping boards the last run continues into the first one
+			if self.wraps(len) && runs.len() > 1 && runs[0].2 == runs[runs.len() - 1].2 && runs[0].2.is_filled() {
+				let last = runs.pop().unwrap();
+				let first = runs.remove(0);
+				if first.1 + last.1 > 2 {
+					errors.push(Violation { rule: Rule::Triplet, regions: vec![(last.0, c1, last.1, 1), (0, c1, first.1, 1)] });
+				}
+			}
+
+			for (start, counter, state) in runs {
+				if counter > 2 && state.is_filled() {
+					errors.push(Violation { rule: Rule::Triplet, regions: vec![(start, c1, counter, 1)] });
+				}
+			}
+
+			for segment in self.segments(c1, len, &get) {
+				let trues = segment.iter().filter(|c2| get(&self.map, c1, **c2) == CellState::True(false)).count();
+				let falses = segment.iter().filter(|c2| get(&self.map, c1, **c2) == CellState::False(false)).count();
+
+				if falses > Self::balance_limit(segment.len()) || trues > Self::balance_limit(segment.len()) {
+					errors.push(Violation { rule: Rule::Balance, regions: Self::line_regions(c1, &segment) });
+				}
+			}
+		}
+
+		for c1_1 in 0..lines {
+			for c1_2 in c1_1+1..lines {
+				let mut are_same = true;
+				let mut any_nones = false;
+				for c2 in 0..len {
+					are_same &= get(&self.map, c1_1, c2) == get(&self.map, c1_2, c2);
+					any_nones |= get(&self.map, c1_1, c2) == CellState::None || get(&self.map, c1_2, c2) == CellState::None;
+				}
+
+				if are_same && !any_nones {
+					errors.push(Violation { rule: Rule::Duplicate, regions: vec![(0, c1_1, len, 1), (0, c1_2, len, 1)] });
+				}
+			}
+		}
+
+		errors
+	}
+
+	// cells of a line that aren't split by blocked cells, without masks that's the whole line
+	pub fn segments<F: Fn(&Vec<Vec<CellState>>, usize, usize) -> CellState>(&self, c1: usize, len: usize, get: &F) -> Vec<Vec<usize>> {
+		let mut segments = vec![Vec::new()];
+
+		for c2 in 0..len {
+			if get(&self.map, c1, c2) == CellState::Blocked {
+				segments.push(Vec::new());
+			}
+			else {
+				segments.last_mut().unwrap().push(c2);
+			}
+		}
+
+		segments.retain(|segment| !segment.is_empty());
+		segments
+	}
+
+	// axis-space rectangles covering the given cells of one line
+	fn line_regions(c1: usize, cells: &[usize]) -> Vec<(usize, usize, usize, usize)> {
+		let mut regions: Vec<(usize, usize, usize, usize)> = Vec::new();
+
+		for c2 in cells {
+			match regions.last_mut() {
+				Some(region) if region.0 + region.2 == *c2 => region.2 += 1,
+				_ => regions.push((*c2, c1, 1, 1)),
+			}
+		}
+
+		regions
+	}
+
+	pub fn get_constraint_errors(&self) -> Vec<Violation> {
+		let mut errors = Vec::new();
+
+		for y in 0..self.height {
+			for x in 0..self.width {
+				if x + 1 < self.width && self.constraints[y][x][0].is_broken(self.map[y][x], self.map[y][x+1]) {
+					errors.push(Violation { rule: Rule::Constraint, regions: vec![(x, y, 2, 1)] });
+				}
+				if y + 1 < self.height && self.constraints[y][x][1].is_broken(self.map[y][x], self.map[y+1][x]) {
+					errors.push(Violation { rule: Rule::Constraint, regions: vec![(x, y, 1, 2)] });
+				}
+			}
+		}
+
+		errors
+	}
+
+	// fills the board with a valid solution. Some masks can't be filled at all, so masked boards get a fresh one every so often
+	pub fn generate_solution(&mut self) {
+		self.generate_solution_with(&mut |_, _| true);
+	}
+
+	// false if report stopped it
+	pub fn generate_solution_with(&mut self, report: &mut dyn FnMut(Phase, f32) -> bool) -> bool {
+		if self.rules.mask == Mask::None {
+			return self.generate_valid_with(report).is_some();
+		}
+
+		loop {
+			self.place_mask(0.12);
+			for _ in 0..50 {
+				if !self.generate_with(report) {
+					return false;
+				}
+				if self.is_valid {
+					return true;
+				}
+				self.reset();
+			}
+		}
+	}
+
+	// blocks about the given fraction of cells, clearing the rest of the board
+	pub fn place_mask(&mut self, fraction: f32) {
+		self.map = vec![vec![CellState::None; self.width]; self.height];
+
+		for y in 0..self.height {
+			for x in 0..self.width {
+				match self.rules.mask {
+					Mask::None => {}
+					Mask::Random => {
+						if rand::gen_range(0.0, 1.0) < fraction {
+							self.map[y][x] = CellState::Blocked;
+						}
+					}
+					Mask::Symmetric => {
+						let (mx, my) = (self.width - 1 - x, self.height - 1 - y);
+						if (y, x) <= (my, mx) && rand::gen_range(0.0, 1.0) < fraction {
+							self.map[y][x] = CellState::Blocked;
+							self.map[my][mx] = CellState::Blocked;
+						}
+					}
+				}
+			}
+		}
+
+		self.verify_board();
+	}
+
+	pub fn generate_valid(&mut self) -> u32 {
+		self.generate_valid_with(&mut |_, _| true).unwrap()
+	}
+
+	// the attempts it took, None if report stopped it
+	pub fn generate_valid_with(&mut self, report: &mut dyn FnMut(Phase, f32) -> bool) -> Option<u32> {
+		let mut i = 1;
+		if !self.generate_with(report) {
+			return None;
+		}
+		while !self.is_valid {
+			self.reset();
+			if !self.generate_with(report) {
+				return None;
+			}
+			i += 1;
+		}
+		
+		Some(i)
+	}
+
+	// attempts on every thread at once, the first valid board wins. Only this thread reports, the others stop with it
+	pub fn generate_valid_racing(&mut self, report: &mut dyn FnMut(Phase, f32) -> bool) -> Option<u32> {
+		let threads = threads();
+		if threads < 2 {
+			return self.generate_valid_with(report);
+		}
+
+		let done = AtomicBool::new(false);
+		let attempts = AtomicU32::new(0);
+		let attempt = |board: &mut Board, report: &mut dyn FnMut(Phase, f32) -> bool| -> Option<bool> {
+			board.reset();
+			attempts.fetch_add(1, Ordering::Relaxed);
+			if !board.generate_with(&mut |phase, d| !done.load(Ordering::Relaxed) && report(phase, d)) {
+				return None;
+			}
+			// two boards can be done at once, only one of them wins
+			Some(board.is_valid && !done.swap(true, Ordering::Relaxed))
+		};
+
+		let mut cancelled = false;
+		let winner = thread::scope(|scope| {
+			let workers: Vec<_> = (1..threads).map(|_| scope.spawn(|| {
+				let mut board = self.clone();
+				while !attempt(&mut board, &mut |_, _| true)? {}
+				Some(board)
+			})).collect();
+
+			let mut board = self.clone();
+			let mut report = |phase, d| {
+				cancelled = !report(phase, d);
+				!cancelled
+			};
+			let mine = loop {
+				match attempt(&mut board, &mut report) {
+					Some(true) => break Some(board),
+					Some(false) => {}
+					None => break None,
+				}
+			};
+			if cancelled {
+				done.store(true, Ordering::Relaxed);
+			}
+			let theirs: Vec<Option<Board>> = workers.into_iter().map(|worker| worker.join().unwrap()).collect();
+			mine.or(theirs.into_iter().flatten().next())
+		});
+
+		if cancelled {
+			return None;
+		}
+		self.map = winner?.map;
+		self.verify_board();
+		Some(attempts.into_inner())
+	}
+
+	pub fn generate(&mut self) {
+		self.generate_with(&mut |_, _| true);
+	}
+
+	// reports the fraction of cells filled after every random one
+	pub fn generate_with(&mut self, report: &mut dyn FnMut(Phase, f32) -> bool) -> bool {
+		let cells = (self.width * self.height) as f32;
+		while self.has_nones() {
+			while self.fill_rows() | self.surround_doubles() | self.separate_triples() {}
+			self.insert_random();
+			if !report(Phase::Filling, 1.0 - self.count_nones() as f32 / cells) {
+				return false;
+			}
+		}
+		self.verify_board();
+		true
+	}
+
+	pub fn generate_fraction(&mut self, fract: f32) {
+		while (self.count_nones() as f32 / (self.width * self.height) as f32) > fract {
+			while self.fill_rows() | self.surround_doubles() | self.separate_triples() {}
+			self.insert_random();
+		}
+		self.verify_board();
+	}
+
+	// clears the board, leaving blocked cells in place
+	pub fn reset(&mut self) {
+		for row in self.map.iter_mut() {
+			for cell in row.iter_mut() {
+				if *cell != CellState::Blocked {
+					*cell = CellState::None;
+				}
+			}
+		}
+		self.constraints = vec![vec![[Constraint::None; 2]; self.width]; self.height];
+		self.verify_board();
+	}
+
+	pub fn insert_random(&mut self) {
+		let nones = self.count_nones() as i32;
+
+		if nones == 0 {
+			return;
+		}
+
+		let mut index = rand::gen_range(0, nones);
+		let mut x = 0; 
+		let mut y = 0;
+		loop {
+			if self.map[y][x] == CellState::None {
+				index -= 1;
+				if index <= 0 {
+					break;
+				}
+			}
+			x += 1;
+			if x >= self.width {
+				x = 0;
+				y += 1;
+			}
+			
+		}
+
+		self.map[y][x] = CellState::from_bool(rand::gen_range(0, 2) == 0);
+	}
+
+	pub fn surround_doubles(&mut self) -> bool {
+		self.surround_doubles_axis(self.height, self.width,
+			|v, y, x| v[y][x], 
+			|v, y, x, s| v[y][x] = s) |
+		self.surround_doubles_axis(self.width, self.height,
+			|v, x, y| v[y][x], 
+			|v, x, y, s| v[y][x] = s)
+	}
+
+	pub fn surround_doubles_axis<
+		F: Fn(&Vec<Vec<CellState>>, usize, usize) -> CellState,
+		G: Fn(&mut Vec<Vec<CellState>>, usize, usize, CellState)
+	>(&mut self, lines: usize, len: usize, get: F, set: G) -> bool {
+		let mut changed = false;
+
+		for c1 in 0..lines {
+			for c2 in 0..len {
+				let state = get(&self.map, c1, c2);
+				let Some(next) = self.neighbour(c2, 1, len) else { continue };
+				if state == get(&self.map, c1, next) && state.is_filled() {
+					for side in [self.neighbour(c2, -1, len), self.neighbour(c2, 2, len)].into_iter().flatten() {
+						if get(&self.map, c1, side) == CellState::None {
+							set(&mut self.map, c1, side, state.inverse());
+							changed = true;
+						}
+					}
+				}
+			}
+		}
+
+		changed
+	}
+
+	pub fn separate_triples(&mut self) -> bool {
+		self.separate_triples_axis(self.height, self.width,
+			|v, y, x| v[y][x], 
+			|v, y, x, s| v[y][x] = s) |
+		self.separate_triples_axis(self.width, self.height,
+			|v, x, y| v[y][x], 
+			|v, x, y, s| v[y][x] = s)
+	}
+
+	pub fn separate_triples_axis<
+		F: Fn(&Vec<Vec<CellState>>, usize, usize) -> CellState,
+		G: Fn(&mut Vec<Vec<CellState>>, usize, usize, CellState)
+	>(&mut self, lines: usize, len: usize, get: F, set: G) -> bool {
+
+		let mut changed = false;
+
+		for c1 in 0..lines {
+			for c2 in 0..len {
+				let (Some(prev), Some(next)) = (self.neighbour(c2, -1, len), self.neighbour(c2, 1, len)) else { continue };
+				let outer = get(&self.map, c1, prev);
+				if outer == get(&self.map, c1, next) && outer.is_filled() && get(&self.map, c1, c2) == CellState::None {
+					set(&mut self.map, c1, c2, outer.inverse());
+					changed = true;
+				}
+			}
+		}
+
+		changed
+	}
+
+	
+
+	pub fn fill_rows(&mut self) -> bool {
+		self.fill_rows_axis(self.height, self.width,
+			|v, y, x| v[y][x], 
+			|v, y, x, s| v[y][x] = s) |
+		self.fill_rows_axis(self.width, self.height,
+			|v, x, y| v[y][x], 
+			|v, x, y, s| v[y][x] = s)
+	}
+
+	pub fn fill_rows_axis<
+		F: Fn(&Vec<Vec<CellState>>, usize, usize) -> CellState,
+		G: Fn(&mut Vec<Vec<CellState>>, usize, usize, CellState)
+	>(&mut self, lines: usize, len: usize, get: F, set: G) -> bool {
+
+		let mut changed = false;
+
+		for c1 in 0..lines {
+			for segment in self.segments(c1, len, &get) {
+				let mut nones = 0;
+				let mut trues = 0;
+				let mut falses = 0;
+
+				for c2 in segment.iter() {
+					match get(&self.map, c1, *c2) {
+						CellState::False(_) => falses += 1,
+						CellState::True(_) => trues += 1,
+						_ => nones += 1,
+					}
+				}
+
+				if trues == Self::balance_limit(segment.len()) && nones != 0 {
+					for c2 in segment.iter() {
+						if get(&self.map, c1, *c2) == CellState::None {
+							set(&mut self.map, c1, *c2, CellState::False(false));
+						}
+					}
+					changed = true;
+				}
+				if falses == Self::balance_limit(segment.len()) && nones != 0 {
+					for c2 in segment.iter() {
+						if get(&self.map, c1, *c2) == CellState::None {
+							set(&mut self.map, c1, *c2, CellState::True(false));
+						}
+					}
+					changed = true;
+				}
+			}
+		}
+
+		changed
+	}
+
+	
+	pub fn degenerate(&mut self) {
+		self.deseparate_triples(0.2);
+		self.desurround_doubles(0.2);
+		self.defill_rows(0.2);
+		self.defill_rows(1.0);
+		self.desurround_doubles(0.4);
+		self.deseparate_triples(0.5);
+		self.desurround_doubles(1.0);
+		self.deseparate_triples(1.0);
+
+
+		self.verify_board();
+	}
+
+	pub fn is_solvable(&self) -> bool {
+		let mut clone = self.clone();
+
+		while clone.surround_doubles() | clone.separate_triples() | clone.fill_rows() | clone.apply_constraints() | clone.extend_equal_pairs() {}
+
+		clone.verify_board();
+
+		clone.is_won
+	}
+
+	pub fn delete_percentage(&mut self, percentage: f32) {
+		for y in 0..self.height {
+			for x in 0..self.width {
+				if rand::gen_range(0.0, 1.0) < percentage {
+					self.map[y][x] = CellState::None;
+				}
+			}
+		}
+	}
+
+	pub fn copy_nones(&mut self, other: &Self) {
+		if other.width != self.width || other.height != self.height {return;}
+
+		for y in 0..self.height {
+			for x in 0..self.width {
+				if other.map[y][x] == CellState::None {
+					self.map[y][x] = CellState::None;
+				}
+			}
+		}
+	}
+
+	pub fn deseparate_triples(&mut self, percentage: f32) -> bool {
+		self.deseparate_triples_axis(self.height, self.width,
+			|v, y, x| v[y][x], 
+			|v, y, x, s| v[y][x] = s, percentage) |
+		self.deseparate_triples_axis(self.width, self.height,
+			|v, x, y| v[y][x], 
+			|v, x, y, s| v[y][x] = s, percentage)
+	}
+
+	pub fn deseparate_triples_axis<
+		F: Fn(&Vec<Vec<CellState>>, usize, usize) -> CellState,
+		G: Fn(&mut Vec<Vec<CellState>>, usize, usize, CellState)
+	>(&mut self, lines: usize, len: usize, get: F, set: G, percentage: f32) -> bool {
+		let mut changed = false;
+
+		for c1 in 0..lines {
+			for c2 in 0..len {
+				let (Some(prev), Some(next)) = (self.neighbour(c2, -1, len), self.neighbour(c2, 1, len)) else { continue };
+				let outer = get(&self.map, c1, prev);
+				if outer == get(&self.map, c1, next) && outer.is_filled() && get(&self.map, c1, c2).is_filled() && rand::gen_range(0.0, 1.0) < percentage {
+					set(&mut self.map, c1, c2, CellState::None);
+					changed = true;
+				}
+			}
+		}
+
+		changed
+	}
+
+
+	
+	pub fn defill_rows(&mut self, percentage: f32) -> bool {
+		self.defill_row_axis(self.height, self.width,
+			|v, y, x| v[y][x], 
+			|v, y, x, s| v[y][x] = s, percentage) |
+		self.defill_row_axis(self.width, self.height,
+			|v, x, y| v[y][x], 
+			|v, x, y, s| v[y][x] = s, percentage)
+	}
+
+	pub fn defill_row_axis<
+		F: Fn(&Vec<Vec<CellState>>, usize, usize) -> CellState,
+		G: Fn(&mut Vec<Vec<CellState>>, usize, usize, CellState)
+	>(&mut self, lines: usize, len: usize, get: F, set: G, percentage: f32) -> bool {
+
+		let mut changed = false;
+
+		for c1 in 0..lines {
+			for segment in self.segments(c1, len, &get) {
+				let mut trues = 0;
+				let mut falses = 0;
+
+				for c2 in segment.iter() {
+					match get(&self.map, c1, *c2) {
+						CellState::False(_) => falses += 1,
+						CellState::True(_) => trues += 1,
+						_ => {},
+					}
+				}
+
+				// only a color whose counterpart is at the limit can be deduced back, in odd segments that's the minority
+				if trues + falses == segment.len() && rand::gen_range(0.0, 1.0) < percentage {
+					let to_delete = 
+						if trues == falses {CellState::from_bool(rand::gen_range(0, 2) == 0)}
+						else {CellState::from_bool(trues < falses)};
+					for c2 in segment.iter() {
+						if get(&self.map, c1, *c2) == to_delete {
+							set(&mut self.map, c1, *c2, CellState::None);
+						}
+					}
+					changed = true;
+				}
+			}
+		}
+
+		changed
+	}
+
+	pub fn desurround_doubles(&mut self, percentage: f32) -> bool {
+		self.desurround_doubles_axis(self.height, self.width,
+			|v, y, x| v[y][x], 
+			|v, y, x, s| v[y][x] = s, percentage) |
+		self.desurround_doubles_axis(self.width, self.height,
+			|v, x, y| v[y][x], 
+			|v, x, y, s| v[y][x] = s, percentage)
+	}
+
+	pub fn desurround_doubles_axis<
+		F: Fn(&Vec<Vec<CellState>>, usize, usize) -> CellState,
+		G: Fn(&mut Vec<Vec<CellState>>, usize, usize, CellState)
+	>(&mut self, lines: usize, len: usize, get: F, set: G, percentage: f32) -> bool {
+		let mut changed = false;
+
+		for c1 in 0..lines {
+			for c2 in 0..len {
+				let state = get(&self.map, c1, c2);
+				let Some(next) = self.neighbour(c2, 1, len) else { continue };
+				if state == get(&self.map, c1, next) && state.is_filled() && rand::gen_range(0.0, 1.0) < percentage {
+					for side in [self.neighbour(c2, -1, len), self.neighbour(c2, 2, len)].into_iter().flatten() {
+						if get(&self.map, c1, side).is_filled() {
+							set(&mut self.map, c1, side, CellState::None);
+							changed = true;
+						}
+					}
+				}
+			}
+		}
+
+		changed
+	}
+
+	pub fn purge_redundancies(&mut self) {
+		self.purge_redundancies_with(&mut |_, _| true);
+	}
+
+	// the fraction reported is how many of the cells that could go at first are gone
+	pub fn purge_redundancies_with(&mut self, report: &mut dyn FnMut(Phase, f32) -> bool) -> bool {
+		let mut first = None;
+		loop {
+			let to_delete = self.deletable();
+			let total = *first.get_or_insert(to_delete.len().max(1));
+			if !report(Phase::Purging, 1.0 - to_delete.len() as f32 / total as f32) {
+				return false;
+			}
+			if !self.delete_random(to_delete) {
+				break;
+			}
+		}
+		self.verify_board();
+		true
+	}
+	
+	pub fn delete_one(&mut self) -> bool {
+		let to_delete = self.deletable();
+		self.delete_random(to_delete)
+	}
+
+	// filled cells the board can still be solved without, in row order. Larger boards split the cells between threads
+	pub fn deletable(&self) -> Vec<(usize, usize)> {
+		let filled: Vec<(usize, usize)> = (0..self.height).flat_map(|y| (0..self.width).map(move |x| (x, y))).filter(|(x, y)| self.map[*y][*x].is_filled()).collect();
+		let threads = threads();
+		if threads < 2 || filled.len() < PARALLEL_CELLS {
+			return self.solvable_without(&filled);
+		}
+
+		let chunk = filled.len().div_ceil(threads);
+		thread::scope(|scope| {
+			let workers: Vec<_> = filled.chunks(chunk).map(|cells| scope.spawn(|| self.solvable_without(cells))).collect();
+			workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect()
+		})
+	}
+
+	fn solvable_without(&self, cells: &[(usize, usize)]) -> Vec<(usize, usize)> {
+		let mut board = self.clone();
+		cells.iter().copied().filter(|&(x, y)| {
+			let temp = board.map[y][x];
+			board.map[y][x] = CellState::None;
+			let solvable = board.is_solvable();
+			board.map[y][x] = temp;
+			solvable
+		}).collect()
+	}
+
+	fn delete_random(&mut self, to_delete: Vec<(usize, usize)>) -> bool {
+		if to_delete.is_empty() {
+			return false;
+		}
+
+		use rand::ChooseRandom;
+		let coords = to_delete.choose().unwrap();
+
+		self.map[coords.1][coords.0] = CellState::None;
+
+		true
+	}
+
+	// puts a marker on the given fraction of edges between two filled cells
+	pub fn place_constraints(&mut self, percentage: f32) {
+		for y in 0..self.height {
+			for x in 0..self.width {
+				if x + 1 < self.width && rand::gen_range(0.0, 1.0) < percentage {
+					self.constraints[y][x][0] = Constraint::between(self.map[y][x], self.map[y][x+1]);
+				}
+				if y + 1 < self.height && rand::gen_range(0.0, 1.0) < percentage {
+					self.constraints[y][x][1] = Constraint::between(self.map[y][x], self.map[y+1][x]);
+				}
+			}
+		}
+	}
+
+	// removes markers the board can be solved without, in random order
+	pub fn purge_constraints(&mut self) {
+		let mut edges = Vec::new();
+		for y in 0..self.height {
+			for x in 0..self.width {
+				for i in 0..2 {
+					if self.constraints[y][x][i] != Constraint::None {
+						edges.push((x, y, i));
+					}
+				}
+			}
+		}
+
+		use rand::ChooseRandom;
+		edges.shuffle();
+
+		for (x, y, i) in edges {
+			let temp = self.constraints[y][x][i];
+			self.constraints[y][x][i] = Constraint::None;
+
+			if !self.is_solvable() {
+				self.constraints[y][x][i] = temp;
+			}
+		}
+	}
+
+	pub fn apply_constraints(&mut self) -> bool {
+		self.apply_constraints_axis(self.height, self.width,
+			|v, y, x| v[y][x], 
+			|v, y, x, s| v[y][x] = s,
+			|c, y, x| c[y][x][0]) |
+		self.apply_constraints_axis(self.width, self.height,
+			|v, x, y| v[y][x], 
+			|v, x, y, s| v[y][x] = s,
+			|c, x, y| c[y][x][1])
+	}
+
+	// a marker next to a filled cell determines the other cell
+	pub fn apply_constraints_axis<
+		F: Fn(&Vec<Vec<CellState>>, usize, usize) -> CellState,
+		G: Fn(&mut Vec<Vec<CellState>>, usize, usize, CellState),
+		H: Fn(&Vec<Vec<[Constraint; 2]>>, usize, usize) -> Constraint
+	>(&mut self, lines: usize, len: usize, get: F, set: G, get_constraint: H) -> bool {
+		let mut changed = false;
+
+		for c1 in 0..lines {
+			for c2 in 0..len-1 {
+				let constraint = get_constraint(&self.constraints, c1, c2);
+				if constraint == Constraint::None {
+					continue;
+				}
+
+				let a = get(&self.map, c1, c2);
+				let b = get(&self.map, c1, c2 + 1);
+				if a.is_filled() && b == CellState::None {
+					set(&mut self.map, c1, c2 + 1, constraint.apply(a));
+					changed = true;
+				}
+				else if a == CellState::None && b.is_filled() {
+					set(&mut self.map, c1, c2, constraint.apply(b));
+					changed = true;
+				}
+			}
+		}
+
+		changed
+	}
+
+	pub fn extend_equal_pairs(&mut self) -> bool {
+		self.extend_equal_pairs_axis(self.height, self.width,
+			|v, y, x| v[y][x], 
+			|v, y, x, s| v[y][x] = s,
+			|c, y, x| c[y][x][0]) |
+		self.extend_equal_pairs_axis(self.width, self.height,
+			|v, x, y| v[y][x], 
+			|v, x, y, s| v[y][x] = s,
+			|c, x, y| c[y][x][1])
+	}
+
+	// two empty cells joined by "=" can't take the value of a cell right next to them, that would make a triplet
+	pub fn extend_equal_pairs_axis<
+		F: Fn(&Vec<Vec<CellState>>, usize, usize) -> CellState,
+		G: Fn(&mut Vec<Vec<CellState>>, usize, usize, CellState),
+		H: Fn(&Vec<Vec<[Constraint; 2]>>, usize, usize) -> Constraint
+	>(&mut self, lines: usize, len: usize, get: F, set: G, get_constraint: H) -> bool {
+		let mut changed = false;
+
+		for c1 in 0..lines {
+			for c2 in 0..len-1 {
+				if get_constraint(&self.constraints, c1, c2) != Constraint::Equal || get(&self.map, c1, c2) != CellState::None || get(&self.map, c1, c2 + 1) != CellState::None {
+					continue;
+				}
+
+				let mut neighbour = CellState::None;
+				if c2 >= 1 {
+					neighbour = get(&self.map, c1, c2 - 1);
+				}
+				if !neighbour.is_filled() && c2 + 2 < len {
+					neighbour = get(&self.map, c1, c2 + 2);
+				}
+
+				if neighbour.is_filled() {
+					set(&mut self.map, c1, c2, neighbour.inverse());
+					set(&mut self.map, c1, c2 + 1, neighbour.inverse());
+					changed = true;
+				}
+			}
+		}
+
+		changed
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn wrapped_4x5_is_not_feasible() {
+		let wrap = Rules { wrap: true, ..Rules::default() };
+		assert!(!Board::is_feasible(4, 5, wrap));
+		assert!(!Board::is_feasible(5, 4, wrap));
+		assert!(Board::is_feasible(4, 5, Rules::default()));
+		assert!(Board::is_feasible(6, 6, wrap));
+	}
+
+	#[test]
+	fn filled_placeholder_is_playable() {
+		let generated = Board::new_serious(6, 6, Rules::default(), 0);
+		assert!(generated.is_generating);
+		let mut queued = Board::new(6, 6, Rules::default(), 3, true);
+		queued.fill_generated(generated.clone(), 10.0, 0.5);
+		assert!(!queued.is_generating);
+		assert_eq!(queued.id, 3);
+		assert!(queued.map == generated.map && queued.constraints == generated.constraints);
+		assert_eq!((queued.generation_end_time, queued.generation_duration), (10.0, 0.5));
+	}
+}
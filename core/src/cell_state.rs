@@ -0,0 +1,70 @@
+#[derive(Copy, Clone, Debug)]
+pub enum CellState {
+	None,
+	True(bool),
+	False(bool),
+	Blocked, // a hole in the board, splits lines into segments
+}
+
+impl CellState {
+	pub fn from_bool(b: bool) -> Self {
+		if b {
+			CellState::True(false)
+		}
+		else {
+			CellState::False(false)
+		}
+	}
+	pub fn next(&self) -> Self {
+		match self {
+			CellState::None => CellState::False(false),
+			CellState::False(_) => CellState::True(false),
+			CellState::True(_) => CellState::None,
+			CellState::Blocked => CellState::Blocked,
+		}
+	}
+
+	pub fn prev(&self) -> Self {
+		match self {
+			CellState::None => CellState::True(false),
+			CellState::False(_) => CellState::None,
+			CellState::True(_) => CellState::False(false),
+			CellState::Blocked => CellState::Blocked,
+		}
+	}
+
+	pub fn inverse(&self) -> Self {
+		match self {
+			CellState::None => CellState::None,
+			CellState::False(_) => CellState::True(false),
+			CellState::True(_) => CellState::False(false),
+			CellState::Blocked => CellState::Blocked,
+		}
+	}
+
+	// holds a value, as opposed to being empty or blocked
+	pub fn is_filled(&self) -> bool {
+		matches!(self, CellState::True(_) | CellState::False(_))
+	}
+
+	pub fn is_locked(&self) -> bool {
+		match self {
+			Self::None => false,
+			Self::False(b) => *b,
+			Self::True(b) => *b,
+			Self::Blocked => false,
+		}
+	}
+}
+
+// ignore the locked part
+impl PartialEq for CellState {
+	fn eq(&self, other: &Self) -> bool {
+		match self {
+			CellState::None => matches!(other, CellState::None),
+			CellState::True(_) => matches!(other, CellState::True(_)),
+			CellState::False(_) => matches!(other, CellState::False(_)),
+			CellState::Blocked => matches!(other, CellState::Blocked),
+		}
+	}
+}
\ No newline at end of file
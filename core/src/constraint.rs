@@ -0,0 +1,45 @@
+use crate::cell_state::CellState;
+
+// Binairo+ marker on the edge between two neighbouring cells
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Constraint {
+	None,
+	Equal,
+	Opposite,
+}
+
+impl Constraint {
+	// the marker that describes two filled cells
+	pub fn between(a: CellState, b: CellState) -> Self {
+		if !a.is_filled() || !b.is_filled() {
+			Constraint::None
+		}
+		else if a == b {
+			Constraint::Equal
+		}
+		else {
+			Constraint::Opposite
+		}
+	}
+
+	pub fn is_broken(&self, a: CellState, b: CellState) -> bool {
+		if !a.is_filled() || !b.is_filled() {
+			return false;
+		}
+
+		match self {
+			Constraint::None => false,
+			Constraint::Equal => a != b,
+			Constraint::Opposite => a == b,
+		}
+	}
+
+	// state forced onto the other cell when one of them is known
+	pub fn apply(&self, known: CellState) -> CellState {
+		match self {
+			Constraint::None => CellState::None,
+			Constraint::Equal => known.inverse().inverse(),
+			Constraint::Opposite => known.inverse(),
+		}
+	}
+}
@@ -0,0 +1,292 @@
+use nanoserde::{DeJson, SerJson};
+
+use crate::{board::Board, cell_state::CellState, constraint::Constraint, rules::{Rules, Mask}, solver::Difficulty};
+
+// Plain text puzzles. A header with the size and the variants, e.g. "8x8" or "6x10 plus wrap", followed by one line per row:
+// '1' and '0' for the two colors, '.' for an empty cell and '#' for a blocked one.
+// Binairo+ boards put a line between every two rows and a character between every two cells, '=' or 'x' for a marker and ' ' for none.
+
+pub fn cell_char(cell: CellState) -> char {
+	match cell {
+		CellState::None => '.',
+		CellState::True(_) => '1',
+		CellState::False(_) => '0',
+		CellState::Blocked => '#',
+	}
+}
+
+pub fn parse_cell(c: char) -> Option<CellState> {
+	match c {
+		'.' => Some(CellState::None),
+		'1' => Some(CellState::True(true)),
+		'0' => Some(CellState::False(true)),
+		'#' => Some(CellState::Blocked),
+		_ => None,
+	}
+}
+
+fn constraint_char(constraint: Constraint) -> char {
+	match constraint {
+		Constraint::None => ' ',
+		Constraint::Equal => '=',
+		Constraint::Opposite => 'x',
+	}
+}
+
+fn parse_constraint(c: char) -> Option<Constraint> {
+	match c {
+		' ' => Some(Constraint::None),
+		'=' => Some(Constraint::Equal),
+		'x' => Some(Constraint::Opposite),
+		_ => None,
+	}
+}
+
+pub fn header(board: &Board) -> String {
+	let mut header = format!("{}x{}", board.width, board.height);
+	if board.rules.constraints {
+		header += " plus";
+	}
+	if board.rules.wrap {
+		header += " wrap";
+	}
+	header
+}
+
+pub fn parse_header(line: &str) -> Result<(usize, usize, Rules), String> {
+	let mut words = line.split_whitespace();
+	let size = words.next().ok_or("missing the board size, e.g. \"8x8\"")?;
+	let (width, height) = size.split_once('x').ok_or(format!("expected a board size like \"8x8\", found \"{size}\""))?;
+	let width: usize = width.parse().map_err(|_| format!("\"{width}\" is not a valid width"))?;
+	let height: usize = height.parse().map_err(|_| format!("\"{height}\" is not a valid height"))?;
+	if width < 2 || height < 2 {
+		return Err(format!("a {width}x{height} board is too small, both sides need at least 2 cells"));
+	}
+
+	let mut rules = Rules::default();
+	for word in words {
+		match word {
+			"plus" => rules.constraints = true,
+			"wrap" => rules.wrap = true,
+			_ => return Err(format!("unknown variant \"{word}\", expected \"plus\" or \"wrap\"")),
+		}
+	}
+
+	Ok((width, height, rules))
+}
+
+// number of text lines the rows of a board take
+pub fn row_lines(height: usize, rules: Rules) -> usize {
+	if rules.constraints {2 * height - 1} else {height}
+}
+
+pub fn rows(board: &Board) -> Vec<String> {
+	let mut lines = Vec::new();
+	for y in 0..board.height {
+		let mut line = String::new();
+		for x in 0..board.width {
+			line.push(cell_char(board.map[y][x]));
+			if board.rules.constraints && x + 1 < board.width {
+				line.push(constraint_char(board.constraints[y][x][0]));
+			}
+		}
+		lines.push(line);
+
+		if board.rules.constraints && y + 1 < board.height {
+			let mut line = String::new();
+			for x in 0..board.width {
+				line.push(constraint_char(board.constraints[y][x][1]));
+				if x + 1 < board.width {
+					line.push(' ');
+				}
+			}
+			lines.push(line.trim_end().to_string());
+		}
+	}
+	lines
+}
+
+// givens are locked, like on generated boards
+pub fn parse_rows(width: usize, height: usize, rules: Rules, lines: &[&str]) -> Result<Board, String> {
+	if lines.len() != row_lines(height, rules) {
+		return Err(format!("expected {} lines for a {width}x{height} board, found {}", row_lines(height, rules), lines.len()));
+	}
+
+	let mut board = Board::new(width, height, rules, 0, false);
+	for (i, line) in lines.iter().enumerate() {
+		// trailing spaces may have been stripped by an editor
+		let chars: Vec<char> = line.trim_end().chars().collect();
+		let char_at = |c: usize| chars.get(c).copied().unwrap_or(' ');
+
+		if rules.constraints && i % 2 == 1 {
+			let y = i / 2;
+			if chars.len() > 2 * width - 1 {
+				return Err(format!("line {} is longer than {} characters", i + 1, 2 * width - 1));
+			}
+			for x in 0..width {
+				board.constraints[y][x][1] = parse_constraint(char_at(2 * x)).ok_or(format!("unknown marker '{}' below row {}, column {}", char_at(2 * x), y + 1, x + 1))?;
+			}
+			continue;
+		}
+
+		let y = if rules.constraints {i / 2} else {i};
+		let step = if rules.constraints {2} else {1};
+		let expected = if rules.constraints {2 * width - 1} else {width};
+		if chars.len() != expected {
+			return Err(format!("row {} has {} characters, expected {expected}", y + 1, chars.len()));
+		}
+		for x in 0..width {
+			board.map[y][x] = parse_cell(char_at(step * x)).ok_or(format!("unknown cell '{}' in row {}, column {}", char_at(step * x), y + 1, x + 1))?;
+			if rules.constraints && x + 1 < width {
+				board.constraints[y][x][0] = parse_constraint(char_at(2 * x + 1)).ok_or(format!("unknown marker '{}' in row {}, after column {}", char_at(2 * x + 1), y + 1, x + 1))?;
+			}
+		}
+	}
+
+	if board.map.iter().flatten().any(|cell| *cell == CellState::Blocked) {
+		board.rules.mask = Mask::Random;
+	}
+	board.verify_board();
+	Ok(board)
+}
+
+pub fn to_text(board: &Board) -> String {
+	let mut text = header(board);
+	for row in rows(board) {
+		text += "\n";
+		text += &row;
+	}
+	text + "\n"
+}
+
+// every puzzle of a text file, separated by blank lines
+pub fn from_text(text: &str) -> Result<Vec<Board>, String> {
+	let lines: Vec<&str> = text.lines().collect();
+	let mut boards = Vec::new();
+	let mut i = 0;
+
+	while i < lines.len() {
+		if lines[i].trim().is_empty() {
+			i += 1;
+			continue;
+		}
+
+		let (width, height, rules) = parse_header(lines[i]).map_err(|e| format!("line {}: {e}", i + 1))?;
+		let n = row_lines(height, rules);
+		let rows = &lines[i + 1..(i + 1 + n).min(lines.len())];
+		let board = parse_rows(width, height, rules, rows).map_err(|e| format!("puzzle at line {}: {e}", i + 1))?;
+		boards.push(board);
+		i += 1 + n;
+	}
+
+	if boards.is_empty() {
+		return Err("no puzzles found".to_string());
+	}
+	Ok(boards)
+}
+
+fn as_lines(rows: &[String]) -> Vec<&str> {
+	rows.iter().map(|row| row.as_str()).collect()
+}
+
+// a puzzle in JSON, its rows use the text format
+#[derive(Clone, Debug, Default, SerJson, DeJson)]
+pub struct JsonPuzzle {
+	#[nserde(default)]
+	pub name: String,
+	pub width: usize,
+	pub height: usize,
+	#[nserde(default)]
+	pub rules: String, // the variants of the text header, e.g. "plus wrap"
+	pub givens: Vec<String>,
+	#[nserde(default)]
+	pub solution: Vec<String>,
+	#[nserde(default)]
+	pub difficulty: String,
+}
+
+impl JsonPuzzle {
+	pub fn new(puzzle: &Board, solution: Option<&Board>, difficulty: Option<Difficulty>) -> Self {
+		let header = header(puzzle);
+		Self {
+			name: String::new(),
+			width: puzzle.width,
+			height: puzzle.height,
+			rules: header.split_once(' ').map(|(_, rules)| rules.to_string()).unwrap_or_default(),
+			givens: rows(puzzle),
+			solution: solution.map(rows).unwrap_or_default(),
+			difficulty: difficulty.map(|d| d.as_str().to_string()).unwrap_or_default(),
+		}
+	}
+
+	// the puzzle and, if present, its solution
+	pub fn boards(&self) -> Result<(Board, Option<Board>), String> {
+		let (width, height, rules) = parse_header(&format!("{}x{} {}", self.width, self.height, self.rules))?;
+		let puzzle = parse_rows(width, height, rules, &as_lines(&self.givens)).map_err(|e| format!("givens: {e}"))?;
+		let solution = if self.solution.is_empty() {
+			None
+		}
+		else {
+			Some(parse_rows(width, height, rules, &as_lines(&self.solution)).map_err(|e| format!("solution: {e}"))?)
+		};
+		if !self.difficulty.is_empty() {
+			Difficulty::parse(&self.difficulty).ok_or(format!("unknown difficulty \"{}\"", self.difficulty))?;
+		}
+
+		Ok((puzzle, solution))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const PUZZLE: &str = "6x6\n.0....\n.....0\n.0.1.0\n..0...\n...1..\n1.0.0.\n";
+	const VARIANTS: &str = "4x4 plus wrap\n1=. . .\n  x\n. . .x#\n\n. . . .\n\n0 . . .\n";
+
+	#[test]
+	fn text_round_trips() {
+		let boards = from_text(&format!("{PUZZLE}\n{VARIANTS}")).unwrap();
+		assert_eq!(boards.len(), 2);
+		assert_eq!(to_text(&boards[0]), PUZZLE);
+		assert_eq!(to_text(&boards[1]), VARIANTS);
+	}
+
+	#[test]
+	fn givens_are_locked() {
+		let board = &from_text(PUZZLE).unwrap()[0];
+		assert!(board.map[0][1].is_locked());
+		assert_eq!(board.map[0][0], CellState::None);
+	}
+
+	#[test]
+	fn variants_are_parsed() {
+		let board = &from_text(VARIANTS).unwrap()[0];
+		assert!(board.rules.constraints && board.rules.wrap);
+		assert_eq!(board.rules.mask, Mask::Random);
+		assert_eq!(board.map[1][3], CellState::Blocked);
+		assert_eq!(board.constraints[0][0][0], Constraint::Equal);
+		assert_eq!(board.constraints[0][1][1], Constraint::Opposite);
+		assert_eq!(board.constraints[1][2][0], Constraint::Opposite);
+	}
+
+	#[test]
+	fn broken_text_is_rejected() {
+		assert!(parse_header("1x8").is_err());
+		assert!(parse_header("8by8").is_err());
+		assert!(parse_header("8x8 plus foo").unwrap_err().contains("foo"));
+		assert!(from_text("6x6\n.0....\n").is_err());
+		assert!(from_text("4x4\n..2.\n....\n....\n....\n").err().unwrap().contains("'2'"));
+		assert!(from_text("4x4\n.....\n....\n....\n....\n").is_err());
+		assert_eq!(from_text("\n\n").err().unwrap(), "no puzzles found");
+	}
+
+	#[test]
+	fn json_round_trips() {
+		let puzzle = &from_text(PUZZLE).unwrap()[0];
+		let json = JsonPuzzle::new(puzzle, Some(puzzle), Some(Difficulty::Medium)).serialize_json();
+		let (givens, solution) = JsonPuzzle::deserialize_json(&json).unwrap().boards().unwrap();
+		assert_eq!(to_text(&givens), PUZZLE);
+		assert_eq!(to_text(&solution.unwrap()), PUZZLE);
+	}
+}
@@ -0,0 +1,174 @@
+use crate::{board::Board, format, rules::Rules};
+
+// Puzzles published for other games. Unruly game IDs from Simon Tatham's puzzle collection, e.g. "6x6:aBbCa...",
+// and bare grids of '0', '1' and '.' (or '-') without a header, as shared by Binairo sites.
+
+// a game ID is "WxH[u][dX]:description". The description walks the cells in reading order: 'a' to 'y' skip 0 to 24 empty cells
+// and then place a 0, 'A' to 'Y' do the same for a 1, and 'z' or 'Z' skip 25 cells without placing anything
+pub fn from_unruly(id: &str) -> Result<Board, String> {
+	let id = id.trim();
+	if id.contains('#') && !id.contains(':') {
+		return Err("this is a random seed, not a game ID. Use \"Game > Specific\" in Unruly to get the ID".to_string());
+	}
+	let (params, description) = id.split_once(':').ok_or("expected an Unruly game ID like \"8x8:aBcD...\", the colon is missing")?;
+
+	// the size is followed by flags for unique lines ('u') and the difficulty ('d' and a letter). Boards here always need unique lines,
+	// a puzzle made without that rule may have another solution or none under it
+	let size_end = params.find(|c: char| !c.is_ascii_digit() && c != 'x').unwrap_or(params.len());
+	let (size, flags) = params.split_at(size_end);
+	let (width, height) = size.split_once('x').ok_or(format!("expected the board size like \"8x8\", found \"{params}\""))?;
+	let width: usize = width.parse().map_err(|_| format!("\"{width}\" is not a valid width"))?;
+	let height: usize = height.parse().map_err(|_| format!("\"{height}\" is not a valid height"))?;
+	if width < 2 || height < 2 || width % 2 == 1 || height % 2 == 1 {
+		return Err(format!("Unruly boards have even sides of at least 2 cells, found {width}x{height}"));
+	}
+	let mut unique = false;
+	let mut flags = flags.chars();
+	while let Some(flag) = flags.next() {
+		match flag {
+			'u' => unique = true,
+			'd' => { flags.next().ok_or("the difficulty flag 'd' needs a letter after it")?; }
+			_ => return Err(format!("unknown parameter '{flag}' in \"{params}\"")),
+		}
+	}
+	if !unique {
+		return Err(format!("\"{params}\" allows repeated rows and columns, only puzzles with the unique rows and columns option ('u' after the size) can be imported"));
+	}
+
+	let cells = width * height;
+	let mut grid = vec!['.'; cells];
+	let mut pos = 0;
+	for (i, c) in description.trim().chars().enumerate() {
+		let (skip, cell) = match c {
+			'z' | 'Z' => (25, None),
+			'a'..='y' => (c as usize - 'a' as usize, Some('0')),
+			'A'..='Y' => (c as usize - 'A' as usize, Some('1')),
+			_ => return Err(format!("unknown character '{c}' at position {} of the description", i + 1)),
+		};
+		pos += skip;
+		if let Some(cell) = cell {
+			// the last letter may place its cell just past the end, to encode trailing empty cells
+			if pos < cells {
+				grid[pos] = cell;
+			}
+			pos += 1;
+		}
+		if pos > cells + 1 {
+			return Err(format!("the description is too long for a {width}x{height} board"));
+		}
+	}
+	if pos < cells {
+		return Err(format!("the description is too short for a {width}x{height} board, it covers {pos} of {cells} cells"));
+	}
+
+	let rows: Vec<String> = grid.chunks(width).map(|row| row.iter().collect()).collect();
+	format::parse_rows(width, height, Rules::default(), &rows.iter().map(|row| row.as_str()).collect::<Vec<_>>())
+}
+
+// one row per line. Cells may be separated by spaces, '-' is an empty cell as well as '.'
+pub fn from_grid(text: &str) -> Result<Board, String> {
+	let mut rows = Vec::new();
+	for (i, line) in text.lines().map(str::trim).filter(|line| !line.is_empty()).enumerate() {
+		let mut row = String::new();
+		for c in line.chars().filter(|c| !c.is_whitespace()) {
+			row.push(match c {
+				'0' | '1' | '.' => c,
+				'-' => '.',
+				_ => return Err(format!("unknown cell '{c}' in row {}, expected '0', '1' or '.'", i + 1)),
+			});
+		}
+		if let Some(first) = rows.first().map(String::len) {
+			if row.len() != first {
+				return Err(format!("row {} has {} cells, the first row has {first}", i + 1, row.len()));
+			}
+		}
+		rows.push(row);
+	}
+
+	let (width, height) = (rows.first().map(String::len).unwrap_or(0), rows.len());
+	if width < 2 || height < 2 {
+		return Err(format!("a {width}x{height} grid is too small, both sides need at least 2 cells"));
+	}
+	let rules = Rules { odd_sizes: width % 2 == 1 || height % 2 == 1, ..Default::default() };
+	if !Board::is_feasible(width, height, rules) {
+		return Err(format!("no {width}x{height} board with unique rows and columns exists"));
+	}
+	format::parse_rows(width, height, rules, &rows.iter().map(|row| row.as_str()).collect::<Vec<_>>())
+}
+
+// every puzzle of a file: Unruly IDs one per line, grids separated by blank lines
+pub fn import(text: &str) -> Result<Vec<Board>, String> {
+	let mut boards = Vec::new();
+	let mut block = Vec::new();
+	let mut block_start = 0;
+	let lines: Vec<&str> = text.lines().chain([""]).collect();
+
+	for (i, line) in lines.iter().enumerate() {
+		let line = line.trim();
+		if line.contains(':') || line.contains('#') {
+			boards.push(from_unruly(line).map_err(|e| format!("line {}: {e}", i + 1))?);
+		}
+		else if !line.is_empty() {
+			if block.is_empty() {
+				block_start = i + 1;
+			}
+			block.push(line);
+		}
+		else if !block.is_empty() {
+			boards.push(from_grid(&block.join("\n")).map_err(|e| format!("grid at line {block_start}: {e}"))?);
+			block.clear();
+		}
+	}
+
+	if boards.is_empty() {
+		return Err("no puzzles found".to_string());
+	}
+	Ok(boards)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const ID: &str = "10x6u:bBACcaBfaeFDHEhcA";
+	const PUZZLE: &str = "10x6\n.0.11..1..\n00.1.....0\n0....0....\n.1...1....\n...1....1.\n......0..0\n";
+
+	#[test]
+	fn reads_unruly_ids_with_unique_lines() {
+		let board = from_unruly(ID).unwrap();
+		assert_eq!(format::to_text(&board), PUZZLE);
+		assert!(board.unique_solution().is_some());
+		assert_eq!(format::to_text(&from_unruly("10x6ude:bBACcaBfaeFDHEhcA").unwrap()), PUZZLE);
+	}
+
+	#[test]
+	fn rejects_unruly_ids_without_unique_lines() {
+		for id in ["10x6:bBACcaBfaeFDHEhcA", "10x6de:bBACcaBfaeFDHEhcA"] {
+			assert!(from_unruly(id).err().unwrap().contains("unique rows and columns"));
+		}
+	}
+
+	#[test]
+	fn rejects_broken_unruly_ids() {
+		assert!(from_unruly("10x6u#12345").err().unwrap().contains("random seed"));
+		assert!(from_unruly("5x6u:a").is_err());
+		assert!(from_unruly("10x6u:bBA").err().unwrap().contains("too short"));
+		assert!(from_unruly("10x6u:bBAC!").err().unwrap().contains("'!'"));
+		assert!(from_unruly("10x6uq:bBACcaBfaeFDHEhcA").err().unwrap().contains("'q'"));
+	}
+
+	#[test]
+	fn reads_grids() {
+		let board = from_grid("1 0 - .\n. . . .\n- - 1 -\n0 . . .").unwrap();
+		assert_eq!(format::to_text(&board), "4x4\n10..\n....\n..1.\n0...\n");
+		assert!(from_grid("10.\n1.").err().unwrap().contains("row 2"));
+		assert!(from_grid("102.\n....").err().unwrap().contains("'2'"));
+	}
+
+	#[test]
+	fn imports_mixed_files() {
+		let boards = import(&format!("{ID}\n\n10\n01\n\n1.\n..\n")).unwrap();
+		assert_eq!(boards.len(), 3);
+		assert!(import("\n").is_err());
+	}
+}
@@ -0,0 +1,14 @@
+// game logic without any ui: the board model, the rules, generation and solving.
+// Usable from tools and servers, the macroquad front end adds drawing and input on top of it
+pub mod board;
+pub mod cell_state;
+pub mod constraint;
+pub mod rules;
+pub mod solver;
+pub mod format;
+pub mod pack;
+pub mod print;
+pub mod pdf;
+pub mod import;
+pub mod replay;
+pub mod net;
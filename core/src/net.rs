@@ -0,0 +1,624 @@
+use std::{io::{BufRead, BufReader, ErrorKind, Write}, net::{Shutdown, SocketAddr, TcpListener, TcpStream}, sync::{Arc, atomic::{AtomicBool, Ordering}, mpsc::{channel, Receiver, RecvTimeoutError, Sender}}, thread, time::{Duration, Instant}};
+
+use crate::{board::Board, cell_state::CellState, format, rules::Rules};
+
+// Games over the network. In a race two players connect to a server, which hands both the same puzzle, passes on how far each of them got
+// and checks the boards they finish with. In co-op up to four players fill in one board together, the server keeps it and decides every move.
+// Every message is a line of text:
+//
+//   hello <name>        client, joining with the player's name
+//   mode <race|coop>    server, what the server is playing, sent first
+//   welcome <index>     server, the player's number in the race, 0 or 1, or in co-op
+//   opponent <name>     server, the other player is here
+//   puzzle <board>      server, a race starts after the countdown
+//   progress <cells>    client: cells filled in so far, server: the opponent's
+//   finish <board>      client, the solved board
+//   rejected <reason>   server, the finished board isn't a solution of the puzzle
+//   result <index> <t>  server, the winner and their time
+//   rematch             client: wants another race, server: the opponent does
+//   left                server, the opponent is gone
+//   full                server, no seat is free
+//
+// Co-op only:
+//
+//   player <index> <name>         server, someone is playing along
+//   gone <index>                  server, they left, the cells they filled stay
+//   set <x> <y> <cell> <version>  client, a move on the cell as of the version the client knows
+//   cell <x> <y> <cell> <owner> <version>
+//                                 server, the cell as it is now, '-' for no owner. Sent to everyone after a move and to the player alone
+//                                 after a move that lost against someone else's on the same version of the cell
+//
+// Boards are in the text format with '|' instead of line breaks, cells are '0', '1' and '.'.
+
+pub const PORT: u16 = 7878;
+pub const COUNTDOWN: f32 = 1.5; // seconds between the puzzle arriving and the clock starting
+pub const COOP_PLAYERS: usize = 4;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+	Race,
+	Coop,
+}
+
+impl Mode {
+	pub fn as_str(&self) -> &str {
+		match self {
+			Mode::Race => "race",
+			Mode::Coop => "coop",
+		}
+	}
+
+	fn seats(&self) -> usize {
+		match self {
+			Mode::Race => 2,
+			Mode::Coop => COOP_PLAYERS,
+		}
+	}
+}
+
+#[derive(Clone)]
+pub enum Message {
+	Hello(String),
+	Welcome(usize),
+	Opponent(String),
+	Puzzle(Board),
+	Progress(usize),
+	Finish(Board),
+	Rejected(String),
+	Result(usize, f32),
+	Rematch,
+	Left,
+	Full,
+	Mode(Mode),
+	Player(usize, String),
+	Gone(usize),
+	Set(usize, usize, CellState, u32),
+	Cell(usize, usize, CellState, Option<usize>, u32),
+}
+
+fn board_line(board: &Board) -> String {
+	format::to_text(board).trim_end().replace('\n', "|")
+}
+
+fn parse_board(line: &str) -> Result<Board, String> {
+	let boards = format::from_text(&line.replace('|', "\n"))?;
+	boards.into_iter().next().ok_or("missing the board".to_string())
+}
+
+impl Message {
+	pub fn to_line(&self) -> String {
+		match self {
+			Message::Hello(name) => format!("hello {name}"),
+			Message::Welcome(index) => format!("welcome {index}"),
+			Message::Opponent(name) => format!("opponent {name}"),
+			Message::Puzzle(board) => format!("puzzle {}", board_line(board)),
+			Message::Progress(cells) => format!("progress {cells}"),
+			Message::Finish(board) => format!("finish {}", board_line(board)),
+			Message::Rejected(reason) => format!("rejected {reason}"),
+			Message::Result(winner, time) => format!("result {winner} {time:.2}"),
+			Message::Rematch => "rematch".to_string(),
+			Message::Left => "left".to_string(),
+			Message::Full => "full".to_string(),
+			Message::Mode(mode) => format!("mode {}", mode.as_str()),
+			Message::Player(index, name) => format!("player {index} {name}"),
+			Message::Gone(index) => format!("gone {index}"),
+			Message::Set(x, y, cell, version) => format!("set {x} {y} {} {version}", format::cell_char(*cell)),
+			Message::Cell(x, y, cell, owner, version) => format!("cell {x} {y} {} {} {version}", format::cell_char(*cell), owner.map(|o| o.to_string()).unwrap_or("-".to_string())),
+		}
+	}
+
+	pub fn from_line(line: &str) -> Result<Self, String> {
+		let line = line.trim_end_matches(['\r', '\n']);
+		let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+		let number = |text: &str| text.trim().parse::<usize>().map_err(|_| format!("\"{text}\" is not a number"));
+		Ok(match word {
+			"hello" => Message::Hello(rest.trim().to_string()),
+			"welcome" => Message::Welcome(number(rest)?),
+			"opponent" => Message::Opponent(rest.trim().to_string()),
+			"puzzle" => Message::Puzzle(parse_board(rest)?),
+			"progress" => Message::Progress(number(rest)?),
+			"finish" => Message::Finish(parse_board(rest)?),
+			"rejected" => Message::Rejected(rest.to_string()),
+			"result" => {
+				let (winner, time) = rest.split_once(' ').ok_or("a result needs the winner and the time")?;
+				Message::Result(number(winner)?, time.parse().map_err(|_| format!("\"{time}\" is not a time"))?)
+			}
+			"rematch" => Message::Rematch,
+			"left" => Message::Left,
+			"full" => Message::Full,
+			"mode" => Message::Mode(match rest.trim() {
+				"race" => Mode::Race,
+				"coop" => Mode::Coop,
+				other => return Err(format!("unknown mode \"{other}\"")),
+			}),
+			"player" => {
+				let (index, name) = rest.split_once(' ').unwrap_or((rest, ""));
+				Message::Player(number(index)?, name.trim().to_string())
+			}
+			"gone" => Message::Gone(number(rest)?),
+			"set" | "cell" => {
+				let words: Vec<&str> = rest.split_whitespace().collect();
+				let (expected, version) = if word == "set" {(4, words.get(3))} else {(5, words.get(4))};
+				if words.len() != expected {
+					return Err(format!("\"{line}\" should have {expected} values"));
+				}
+				let cell = match words[2] {
+					"0" => CellState::False(false),
+					"1" => CellState::True(false),
+					"." => CellState::None,
+					other => return Err(format!("unknown cell \"{other}\"")),
+				};
+				let version = version.unwrap().parse().map_err(|_| format!("\"{}\" is not a version", version.unwrap()))?;
+				let (x, y) = (number(words[0])?, number(words[1])?);
+				if word == "set" {
+					Message::Set(x, y, cell, version)
+				}
+				else {
+					let owner = if words[3] == "-" {None} else {Some(number(words[3])?)};
+					Message::Cell(x, y, cell, owner, version)
+				}
+			}
+			_ => return Err(format!("unknown message \"{line}\"")),
+		})
+	}
+}
+
+fn send(mut stream: &TcpStream, message: &Message) -> Result<(), String> {
+	stream.write_all(format!("{}\n", message.to_line()).as_bytes()).map_err(|e| e.to_string())
+}
+
+// passes every line of the stream on until it closes, then calls back once
+fn read_lines<F: Fn(String) -> bool + Send + 'static, G: FnOnce() + Send + 'static>(stream: &TcpStream, on_line: F, on_close: G) -> Result<(), String> {
+	let reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+	thread::spawn(move || {
+		for line in reader.lines() {
+			let Ok(line) = line else { break };
+			if !on_line(line) {
+				break;
+			}
+		}
+		on_close();
+	});
+	Ok(())
+}
+
+// a connection to a server. Messages arrive on a thread of their own, so the game can poll them every frame
+pub struct Client {
+	stream: TcpStream,
+	receiver: Receiver<Message>,
+}
+
+impl Client {
+	pub fn connect(address: &str, name: &str) -> Result<Self, String> {
+		let stream = TcpStream::connect(address).map_err(|e| format!("can't connect to {address}: {e}"))?;
+		stream.set_nodelay(true).map_err(|e| e.to_string())?;
+		let (sender, receiver) = channel();
+		let closed = sender.clone();
+		// lines that don't parse are skipped, a newer server may know more messages
+		read_lines(&stream, move |line| Message::from_line(&line).map(|message| sender.send(message).is_ok()).unwrap_or(true), move || { let _ = closed.send(Message::Left); })?;
+		let client = Client { stream, receiver };
+		client.send(&Message::Hello(name.to_string()))?;
+		Ok(client)
+	}
+
+	pub fn send(&self, message: &Message) -> Result<(), String> {
+		send(&self.stream, message)
+	}
+
+	pub fn try_recv(&self) -> Option<Message> {
+		self.receiver.try_recv().ok()
+	}
+
+	pub fn recv_timeout(&self, timeout: Duration) -> Option<Message> {
+		self.receiver.recv_timeout(timeout).ok()
+	}
+}
+
+impl Drop for Client {
+	fn drop(&mut self) {
+		let _ = self.stream.shutdown(Shutdown::Both);
+	}
+}
+
+enum Event {
+	Joined(usize, TcpStream),
+	Line(usize, String),
+	Closed(usize),
+	Generated(usize, Board), // the puzzle for a round
+}
+
+struct Seat {
+	id: usize,
+	stream: TcpStream,
+	name: Option<String>, // set once the player said hello
+	rematch: bool,
+}
+
+struct Race {
+	puzzle: Board,
+	started: Instant,
+	winner: Option<usize>,
+}
+
+// the board everyone in a co-op game fills in
+struct Shared {
+	puzzle: Board,
+	board: Board,
+	owners: Vec<Vec<Option<usize>>>, // who filled each cell in
+	versions: Vec<Vec<u32>>, // moves made on each cell
+	started: Instant,
+	solved: bool,
+}
+
+impl Shared {
+	fn new(puzzle: Board) -> Self {
+		let (width, height) = (puzzle.width, puzzle.height);
+		Shared { board: puzzle.clone(), puzzle, owners: vec![vec![None; width]; height], versions: vec![vec![0; width]; height], started: Instant::now(), solved: false }
+	}
+
+	fn cell(&self, x: usize, y: usize) -> Message {
+		Message::Cell(x, y, self.board.map[y][x], self.owners[y][x], self.versions[y][x])
+	}
+}
+
+// the game runs on one thread, connections only hand it their lines and puzzles are generated on threads of their own.
+// Seats are numbered by their place in a race and by their id in co-op, so that cells keep their owner when someone leaves
+struct Game {
+	mode: Mode,
+	width: usize,
+	height: usize,
+	rules: Rules,
+	seats: Vec<Seat>,
+	race: Option<Race>,
+	shared: Option<Shared>,
+	events: Sender<Event>,
+	round: usize, // puzzles generated for an earlier round are dropped
+	generating: bool,
+}
+
+impl Game {
+	fn seat(&self, id: usize) -> Option<usize> {
+		self.seats.iter().position(|seat| seat.id == id)
+	}
+
+	fn send(&self, index: usize, message: &Message) {
+		// a failed write shows up as the connection closing
+		let _ = send(&self.seats[index].stream, message);
+	}
+
+	fn broadcast(&self, message: &Message) {
+		for i in 0..self.seats.len() {
+			self.send(i, message);
+		}
+	}
+
+	// bigger puzzles take seconds, the game keeps handling lines until this one arrives
+	fn generate(&mut self) {
+		self.round += 1;
+		self.generating = true;
+		let (width, height, rules, round, events) = (self.width, self.height, self.rules, self.round, self.events.clone());
+		thread::spawn(move || {
+			let _ = events.send(Event::Generated(round, Board::new_serious(width, height, rules, 0)));
+		});
+	}
+
+	// a round that's waiting for its puzzle won't be started again
+	fn cancel(&mut self) {
+		self.round += 1;
+		self.generating = false;
+	}
+
+	fn start_race(&mut self) {
+		self.race = None;
+		for seat in &mut self.seats {
+			seat.rematch = false;
+		}
+		self.generate();
+	}
+
+	fn begin_race(&mut self, puzzle: Board) {
+		for i in 0..self.seats.len() {
+			self.send(i, &Message::Welcome(i));
+			self.send(i, &Message::Puzzle(puzzle.clone()));
+		}
+		self.race = Some(Race { puzzle, started: Instant::now(), winner: None });
+	}
+
+	// everyone gets the puzzle and every move made so far
+	fn send_shared(&self, index: usize) {
+		let Some(shared) = &self.shared else { return };
+		self.send(index, &Message::Puzzle(shared.puzzle.clone()));
+		for y in 0..shared.board.height {
+			for x in 0..shared.board.width {
+				if shared.versions[y][x] > 0 {
+					self.send(index, &shared.cell(x, y));
+				}
+			}
+		}
+	}
+
+	fn start_shared(&mut self) {
+		self.shared = None;
+		for seat in &mut self.seats {
+			seat.rematch = false;
+		}
+		self.generate();
+	}
+
+	// players who joined while it was generated get it as well
+	fn begin_shared(&mut self, puzzle: Board) {
+		self.shared = Some(Shared::new(puzzle));
+		for i in 0..self.seats.len() {
+			self.send_shared(i);
+		}
+	}
+
+	fn handle(&mut self, event: Event) {
+		match event {
+			Event::Joined(id, stream) => {
+				let _ = send(&stream, &Message::Mode(self.mode));
+				if self.seats.len() >= self.mode.seats() {
+					let _ = send(&stream, &Message::Full);
+					let _ = stream.shutdown(Shutdown::Both);
+					return;
+				}
+				self.seats.push(Seat { id, stream, name: None, rematch: false });
+			}
+			Event::Line(id, line) => {
+				let Some(index) = self.seat(id) else { return };
+				match Message::from_line(&line) {
+					Ok(message) => self.handle_message(index, message),
+					Err(e) => self.send(index, &Message::Rejected(e)),
+				}
+			}
+			Event::Closed(id) => {
+				let Some(index) = self.seat(id) else { return };
+				self.seats.remove(index);
+				match self.mode {
+					Mode::Race => {
+						self.race = None;
+						self.cancel();
+						self.broadcast(&Message::Left);
+					}
+					Mode::Coop => {
+						self.broadcast(&Message::Gone(id));
+						if self.seats.is_empty() {
+							self.shared = None;
+							self.cancel();
+						}
+					}
+				}
+			}
+			Event::Generated(round, puzzle) => {
+				if round != self.round || !self.generating {
+					return;
+				}
+				self.generating = false;
+				match self.mode {
+					Mode::Race => self.begin_race(puzzle),
+					Mode::Coop => self.begin_shared(puzzle),
+				}
+			}
+		}
+	}
+
+	fn handle_message(&mut self, index: usize, message: Message) {
+		if self.mode == Mode::Coop {
+			self.handle_coop(index, message);
+			return;
+		}
+		let other = 1 - index.min(1);
+		match message {
+			Message::Hello(name) => {
+				self.seats[index].name = Some(name);
+				self.send(index, &Message::Welcome(index));
+				if self.seats.len() == 2 && self.seats.iter().all(|seat| seat.name.is_some()) {
+					for i in 0..2 {
+						self.send(i, &Message::Opponent(self.seats[1 - i].name.clone().unwrap_or_default()));
+					}
+					self.start_race();
+				}
+			}
+			Message::Progress(cells) if self.race.as_ref().is_some_and(|race| race.winner.is_none()) && other < self.seats.len() => {
+				self.send(other, &Message::Progress(cells));
+			}
+			Message::Finish(board) => {
+				let Some(race) = &mut self.race else { return };
+				if race.winner.is_some() {
+					return;
+				}
+				match check_solution(&race.puzzle, board) {
+					Ok(()) => {
+						race.winner = Some(index);
+						let time = (race.started.elapsed().as_secs_f32() - COUNTDOWN).max(0.0);
+						self.broadcast(&Message::Result(index, time));
+					}
+					Err(e) => self.send(index, &Message::Rejected(e)),
+				}
+			}
+			Message::Rematch => {
+				if self.race.as_ref().is_none_or(|race| race.winner.is_none()) {
+					return;
+				}
+				self.seats[index].rematch = true;
+				if other < self.seats.len() {
+					self.send(other, &Message::Rematch);
+				}
+				if self.seats.len() == 2 && self.seats.iter().all(|seat| seat.rematch) {
+					self.start_race();
+				}
+			}
+			_ => {}
+		}
+	}
+}
+
+impl Game {
+	fn handle_coop(&mut self, index: usize, message: Message) {
+		let id = self.seats[index].id;
+		match message {
+			Message::Hello(name) => {
+				self.send(index, &Message::Welcome(id));
+				for (i, seat) in self.seats.iter().enumerate() {
+					if let (Some(other), false) = (&seat.name, i == index) {
+						self.send(index, &Message::Player(seat.id, other.clone()));
+						self.send(i, &Message::Player(id, name.clone()));
+					}
+				}
+				self.seats[index].name = Some(name);
+				if self.shared.is_none() {
+					if !self.generating {
+						self.start_shared();
+					}
+				}
+				else {
+					self.send_shared(index);
+				}
+			}
+			Message::Set(x, y, cell, version) => {
+				let Some(shared) = &mut self.shared else { return };
+				if x >= shared.board.width || y >= shared.board.height {
+					self.send(index, &Message::Rejected(format!("there is no cell {x}, {y}")));
+					return;
+				}
+				if shared.puzzle.map[y][x].is_locked() || shared.puzzle.map[y][x] == CellState::Blocked {
+					self.send(index, &Message::Rejected(format!("the cell in row {}, column {} is given", y + 1, x + 1)));
+					return;
+				}
+				// the first move on a version of the cell wins, the others are corrected
+				if shared.solved || version != shared.versions[y][x] {
+					let cell = shared.cell(x, y);
+					self.send(index, &cell);
+					return;
+				}
+
+				shared.board.map[y][x] = cell;
+				shared.owners[y][x] = if cell.is_filled() {Some(id)} else {None};
+				shared.versions[y][x] += 1;
+				shared.board.verify_board();
+				let cell = shared.cell(x, y);
+				let solved = shared.board.is_won;
+				shared.solved = solved;
+				let time = (shared.started.elapsed().as_secs_f32() - COUNTDOWN).max(0.0);
+				self.broadcast(&cell);
+				if solved {
+					self.broadcast(&Message::Result(id, time));
+				}
+			}
+			Message::Rematch => {
+				if !self.shared.as_ref().is_some_and(|shared| shared.solved) {
+					return;
+				}
+				self.seats[index].rematch = true;
+				for i in (0..self.seats.len()).filter(|i| *i != index) {
+					self.send(i, &Message::Rematch);
+				}
+				if self.seats.iter().all(|seat| seat.rematch) {
+					self.start_shared();
+				}
+			}
+			_ => {}
+		}
+	}
+}
+
+// the board has to keep every given of the puzzle and follow all the rules
+pub fn check_solution(puzzle: &Board, mut board: Board) -> Result<(), String> {
+	if (board.width, board.height, board.rules.constraints, board.rules.wrap) != (puzzle.width, puzzle.height, puzzle.rules.constraints, puzzle.rules.wrap) {
+		return Err("the board doesn't have the size of the puzzle".to_string());
+	}
+	for y in 0..puzzle.height {
+		for x in 0..puzzle.width {
+			let (given, cell) = (puzzle.map[y][x], board.map[y][x]);
+			let kept = if given.is_filled() {format::cell_char(given) == format::cell_char(cell)} else {(given == CellState::Blocked) == (cell == CellState::Blocked)};
+			if !kept {
+				return Err(format!("the cell in row {}, column {} isn't the given one", y + 1, x + 1));
+			}
+		}
+	}
+	if board.constraints != puzzle.constraints {
+		return Err("the markers don't match the puzzle".to_string());
+	}
+	board.rules = puzzle.rules;
+	board.verify_board();
+	if !board.is_won {
+		return Err("the board isn't solved".to_string());
+	}
+	Ok(())
+}
+
+// a server on its own threads, it stops once dropped
+pub struct Server {
+	address: SocketAddr,
+	running: Arc<AtomicBool>,
+}
+
+impl Server {
+	pub fn start(address: &str, mode: Mode, width: usize, height: usize, rules: Rules) -> Result<Self, String> {
+		if !Board::is_feasible(width, height, rules) {
+			return Err(format!("no {width}x{height} board with unique rows and columns exists"));
+		}
+		let listener = TcpListener::bind(address).map_err(|e| format!("can't listen on {address}: {e}"))?;
+		let local = listener.local_addr().map_err(|e| e.to_string())?;
+		// accepting polls, so the server notices when it should stop
+		listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+		let running = Arc::new(AtomicBool::new(true));
+		let (sender, receiver) = channel();
+
+		let events = sender.clone();
+		let accepting = running.clone();
+		thread::spawn(move || {
+			let mut next_id = 0;
+			while accepting.load(Ordering::Relaxed) {
+				match listener.accept() {
+					Ok((stream, _)) => {
+						let id = next_id;
+						next_id += 1;
+						// a connection that fails this early is dropped
+						let _ = accept(stream, id, &sender);
+					}
+					Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(20)),
+					Err(_) => break,
+				}
+			}
+		});
+
+		let playing = running.clone();
+		thread::spawn(move || {
+			let mut game = Game { mode, width, height, rules, seats: Vec::new(), race: None, shared: None, events, round: 0, generating: false };
+			while playing.load(Ordering::Relaxed) {
+				match receiver.recv_timeout(Duration::from_millis(50)) {
+					Ok(event) => game.handle(event),
+					Err(RecvTimeoutError::Timeout) => {}
+					Err(RecvTimeoutError::Disconnected) => break,
+				}
+			}
+			for seat in &game.seats {
+				let _ = seat.stream.shutdown(Shutdown::Both);
+			}
+		});
+
+		Ok(Server { address: local, running })
+	}
+
+	pub fn address(&self) -> SocketAddr {
+		self.address
+	}
+}
+
+// the game hears of the player before any of their lines
+fn accept(stream: TcpStream, id: usize, sender: &Sender<Event>) -> Result<(), String> {
+	stream.set_nonblocking(false).map_err(|e| e.to_string())?;
+	stream.set_nodelay(true).map_err(|e| e.to_string())?;
+	let reading = stream.try_clone().map_err(|e| e.to_string())?;
+	sender.send(Event::Joined(id, stream)).map_err(|e| e.to_string())?;
+	let lines = sender.clone();
+	let closed = sender.clone();
+	read_lines(&reading, move |line| lines.send(Event::Line(id, line)).is_ok(), move || { let _ = closed.send(Event::Closed(id)); })
+}
+
+impl Drop for Server {
+	fn drop(&mut self) {
+		self.running.store(false, Ordering::Relaxed);
+	}
+}
@@ -0,0 +1,70 @@
+use nanoserde::{DeJson, SerJson};
+
+use crate::{board::Board, format::JsonPuzzle, solver::Difficulty};
+
+// a curated set of puzzles, distributed as JSON
+#[derive(Clone, Debug, Default, SerJson, DeJson)]
+pub struct Pack {
+	pub title: String,
+	#[nserde(default)]
+	pub author: String,
+	pub puzzles: Vec<JsonPuzzle>,
+}
+
+// a pack puzzle that passed validation
+#[derive(Clone)]
+pub struct PackPuzzle {
+	pub name: String,
+	pub board: Board,
+	pub solution: Board,
+	pub difficulty: Difficulty,
+}
+
+#[derive(Clone)]
+pub struct LoadedPack {
+	pub title: String,
+	pub author: String,
+	pub puzzles: Vec<PackPuzzle>,
+}
+
+impl Pack {
+	pub fn load(json: &str) -> Result<LoadedPack, String> {
+		Self::deserialize_json(json).map_err(|e| e.to_string())?.validate()
+	}
+
+	// every puzzle has to be well formed and have exactly one solution, matching the included one
+	pub fn validate(&self) -> Result<LoadedPack, String> {
+		if self.title.trim().is_empty() {
+			return Err("the pack has no title".to_string());
+		}
+		if self.puzzles.is_empty() {
+			return Err(format!("\"{}\" has no puzzles", self.title));
+		}
+
+		let mut puzzles = Vec::new();
+		for (i, puzzle) in self.puzzles.iter().enumerate() {
+			let name = if puzzle.name.is_empty() {format!("#{}", i + 1)} else {puzzle.name.clone()};
+			let error = |e: &str| format!("puzzle {name}: {e}");
+
+			let (board, solution) = puzzle.boards().map_err(|e| error(&e))?;
+			let solution = solution.ok_or(error("missing the solution"))?;
+			if let Some(violation) = board.errors.first() {
+				return Err(error(&format!("the givens break the {:?} rule", violation.rule).to_lowercase()));
+			}
+			let found = board.unique_solution().ok_or(error("doesn't have exactly one solution"))?;
+			if found.map != solution.map {
+				return Err(error("the included solution is wrong"));
+			}
+			let difficulty = if puzzle.difficulty.is_empty() {
+				board.grade().unwrap_or_default()
+			}
+			else {
+				Difficulty::parse(&puzzle.difficulty).ok_or(error(&format!("unknown difficulty \"{}\"", puzzle.difficulty)))?
+			};
+
+			puzzles.push(PackPuzzle { name, board, solution, difficulty });
+		}
+
+		Ok(LoadedPack { title: self.title.clone(), author: self.author.clone(), puzzles })
+	}
+}
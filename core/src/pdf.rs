@@ -0,0 +1,140 @@
+use crate::print::{Page, Shape, PAGE_WIDTH, PAGE_HEIGHT};
+
+// A minimal PDF writer for printed sheets: uncompressed content streams and the built-in Helvetica font, so nothing gets embedded.
+
+// Helvetica advance widths of the printable ASCII characters, in thousandths of the font size
+const HELVETICA: [u16; 95] = [
+	278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+	556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+	1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+	667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+	333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+	556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+// the standard font only covers ASCII here, anything else is printed as '?'
+fn ascii(c: char) -> char {
+	if (' '..='~').contains(&c) {c} else {'?'}
+}
+
+pub fn text_width(text: &str, size: f32) -> f32 {
+	text.chars().map(|c| HELVETICA[ascii(c) as usize - 32] as f32).sum::<f32>() * size / 1000.0
+}
+
+fn escape(text: &str) -> String {
+	let mut escaped = String::new();
+	for c in text.chars().map(ascii) {
+		if matches!(c, '\\' | '(' | ')') {
+			escaped.push('\\');
+		}
+		escaped.push(c);
+	}
+	escaped
+}
+
+// drawing operators of a page, flipping y since PDF counts from the bottom
+fn content(page: &Page) -> String {
+	let mut ops = String::new();
+	for shape in &page.shapes {
+		ops += &match shape {
+			Shape::Line { from, to, width, gray } => format!("{width} w 2 J {gray:.2} G {:.2} {:.2} m {:.2} {:.2} l S\n", from.0, PAGE_HEIGHT - from.1, to.0, PAGE_HEIGHT - to.1),
+			Shape::Rect { x, y, w, h, gray } => format!("{gray:.2} g {x:.2} {:.2} {w:.2} {h:.2} re f\n", PAGE_HEIGHT - y - h),
+			Shape::Circle { center, r, fill, stroke } => {
+				// four bezier quarters
+				let (x, y) = (center.0, PAGE_HEIGHT - center.1);
+				let k = r * 0.5523;
+				format!(
+					"0.3 w {fill:.2} g {stroke:.2} G {:.2} {y:.2} m {:.2} {:.2} {:.2} {:.2} {x:.2} {:.2} c {:.2} {:.2} {:.2} {:.2} {:.2} {y:.2} c {:.2} {:.2} {:.2} {:.2} {x:.2} {:.2} c {:.2} {:.2} {:.2} {:.2} {:.2} {y:.2} c B\n",
+					x + r,
+					x + r, y + k, x + k, y + r, y + r,
+					x - k, y + r, x - r, y + k, x - r,
+					x - r, y - k, x - k, y - r, y - r,
+					x + k, y - r, x + r, y - k, x + r,
+				)
+			}
+			Shape::Text { center, size, gray, text } => {
+				// centered on the box of the digits, whose height is about 0.7 of the font size
+				let x = center.0 - text_width(text, *size) / 2.0;
+				let y = PAGE_HEIGHT - center.1 - size * 0.35;
+				format!("BT /F1 {size:.2} Tf {gray:.2} g {x:.2} {y:.2} Td ({}) Tj ET\n", escape(text))
+			}
+		};
+	}
+	ops
+}
+
+pub fn pdf(pages: &[Page]) -> Vec<u8> {
+	// objects 1 to 3 are the catalog, the page tree and the font, then a page and its content for every page
+	let mut objects = vec![
+		"<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+		format!("<< /Type /Pages /Kids [{}] /Count {} >>", (0..pages.len()).map(|i| format!("{} 0 R", 4 + 2 * i)).collect::<Vec<_>>().join(" "), pages.len()),
+		"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_string(),
+	];
+	for (i, page) in pages.iter().enumerate() {
+		objects.push(format!("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>", 5 + 2 * i));
+		let content = content(page);
+		objects.push(format!("<< /Length {} >>\nstream\n{content}endstream", content.len()));
+	}
+
+	let mut pdf = "%PDF-1.4\n".to_string();
+	let mut offsets = Vec::new();
+	for (i, object) in objects.iter().enumerate() {
+		offsets.push(pdf.len());
+		pdf += &format!("{} 0 obj\n{object}\nendobj\n", i + 1);
+	}
+
+	let xref = pdf.len();
+	pdf += &format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+	for offset in offsets {
+		pdf += &format!("{offset:010} 00000 n \n");
+	}
+	pdf += &format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n", objects.len() + 1);
+	pdf.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{format, print::{self, SheetPuzzle}};
+
+	fn pages() -> Vec<Page> {
+		let givens = format::from_text("4x4\n.01.\n.0..\n0...\n..1.\n").unwrap().remove(0);
+		let solution = givens.unique_solution();
+		print::layout("Sheet (1)", &[SheetPuzzle { label: "4x4".to_string(), givens, solution }], 1, true)
+	}
+
+	#[test]
+	fn cross_references_point_at_the_objects() {
+		let pdf = String::from_utf8(pdf(&pages())).unwrap();
+		assert!(pdf.starts_with("%PDF-1.4\n") && pdf.ends_with("%%EOF\n"));
+		assert!(pdf.contains("/Count 2"));
+
+		let xref: usize = pdf.split("startxref\n").nth(1).unwrap().lines().next().unwrap().parse().unwrap();
+		assert!(pdf[xref..].starts_with("xref\n"));
+		let offsets = pdf[xref..].lines().skip(3).take_while(|line| line.ends_with(" n "));
+		let mut objects = 0;
+		for (i, line) in offsets.enumerate() {
+			let offset: usize = line[..10].parse().unwrap();
+			assert!(pdf[offset..].starts_with(&format!("{} 0 obj\n", i + 1)));
+			objects += 1;
+		}
+		// the catalog, the page tree, the font and a page and its content for both pages
+		assert_eq!(objects, 3 + 2 * 2);
+	}
+
+	#[test]
+	fn stream_lengths_match() {
+		let pdf = String::from_utf8(pdf(&pages())).unwrap();
+		for part in pdf.split("<< /Length ").skip(1) {
+			let (length, rest) = part.split_once(" >>\nstream\n").unwrap();
+			let stream = rest.split("endstream").next().unwrap();
+			assert_eq!(length.parse::<usize>().unwrap(), stream.len());
+		}
+	}
+
+	#[test]
+	fn text_is_escaped() {
+		assert_eq!(escape("a (b) \\ é"), "a \\(b\\) \\\\ ?");
+		assert_eq!(text_width("é", 10.0), text_width("?", 10.0));
+	}
+}
@@ -0,0 +1,234 @@
+use crate::{board::Board, cell_state::CellState, constraint::Constraint};
+
+// Printable puzzle sheets. Puzzles are laid out as shapes on A4 pages, which are then written as SVG or PDF.
+// Coordinates are in points, from the top left corner of the page.
+
+pub const PAGE_WIDTH: f32 = 595.0;
+pub const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 40.0;
+const HEADER: f32 = 40.0;
+
+#[derive(Clone, Debug)]
+pub enum Shape {
+	Line { from: (f32, f32), to: (f32, f32), width: f32, gray: f32 },
+	Rect { x: f32, y: f32, w: f32, h: f32, gray: f32 },
+	Circle { center: (f32, f32), r: f32, fill: f32, stroke: f32 }, // fill and stroke grays, stroke is 0.3pt wide
+	Text { center: (f32, f32), size: f32, gray: f32, text: String },
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Page {
+	pub shapes: Vec<Shape>,
+}
+
+pub struct SheetPuzzle {
+	pub label: String,
+	pub givens: Board,
+	pub solution: Option<Board>,
+}
+
+// pages of puzzles, followed by pages with the solutions of those that have one
+pub fn layout(title: &str, puzzles: &[SheetPuzzle], per_page: usize, answers: bool) -> Vec<Page> {
+	let per_page = per_page.max(1);
+	let mut pages = Vec::new();
+
+	for chunk in puzzles.chunks(per_page) {
+		let mut page = Page::default();
+		page.text((PAGE_WIDTH / 2.0, MARGIN), 18.0, 0.0, title);
+		for (i, puzzle) in chunk.iter().enumerate() {
+			let slot = slot(i, per_page);
+			page.puzzle(slot, &puzzle.label, &puzzle.givens, None);
+		}
+		pages.push(page);
+	}
+
+	if answers {
+		let solved: Vec<&SheetPuzzle> = puzzles.iter().filter(|p| p.solution.is_some()).collect();
+		// answers take less room, twice as many fit on a page
+		let per_page = (per_page * 2).min(12);
+		for chunk in solved.chunks(per_page) {
+			let mut page = Page::default();
+			page.text((PAGE_WIDTH / 2.0, MARGIN), 18.0, 0.0, &format!("{title} - answers"));
+			for (i, puzzle) in chunk.iter().enumerate() {
+				page.puzzle(slot(i, per_page), &puzzle.label, &puzzle.givens, puzzle.solution.as_ref());
+			}
+			pages.push(page);
+		}
+	}
+
+	pages
+}
+
+// x, y, w, h of the i-th puzzle on a page, one column for a single puzzle and two otherwise
+fn slot(i: usize, per_page: usize) -> (f32, f32, f32, f32) {
+	let columns = if per_page == 1 {1} else {2};
+	let rows = per_page.div_ceil(columns);
+	let w = (PAGE_WIDTH - 2.0 * MARGIN) / columns as f32;
+	let h = (PAGE_HEIGHT - 2.0 * MARGIN - HEADER) / rows as f32;
+	(MARGIN + (i % columns) as f32 * w, MARGIN + HEADER + (i / columns) as f32 * h, w, h)
+}
+
+impl Page {
+	fn line(&mut self, from: (f32, f32), to: (f32, f32), width: f32, gray: f32) {
+		self.shapes.push(Shape::Line { from, to, width, gray });
+	}
+
+	fn text(&mut self, center: (f32, f32), size: f32, gray: f32, text: &str) {
+		self.shapes.push(Shape::Text { center, size, gray, text: text.to_string() });
+	}
+
+	// a labeled grid with coordinates, solved cells that weren't given are gray
+	fn puzzle(&mut self, (x, y, w, h): (f32, f32, f32, f32), label: &str, givens: &Board, solution: Option<&Board>) {
+		let label_h = 20.0;
+		let pad = 10.0;
+		let coords = 14.0;
+		let cell = ((w - 2.0 * pad - coords) / givens.width as f32).min((h - 2.0 * pad - label_h - coords) / givens.height as f32);
+		let grid_w = cell * givens.width as f32;
+		let grid_h = cell * givens.height as f32;
+		let left = x + coords + (w - coords - grid_w) / 2.0;
+		let top = y + label_h + coords + pad;
+
+		let mut label = label.to_string();
+		if givens.rules.wrap {
+			label += " (wraps around)";
+		}
+		self.text((left + grid_w / 2.0, y + label_h / 2.0 + pad / 2.0), 11.0, 0.0, &label);
+
+		let coord_size = (cell * 0.45).min(9.0);
+		for cx in 0..givens.width {
+			self.text((left + (cx as f32 + 0.5) * cell, top - coords / 2.0), coord_size, 0.4, &column_name(cx));
+		}
+		for cy in 0..givens.height {
+			self.text((left - coords / 2.0, top + (cy as f32 + 0.5) * cell), coord_size, 0.4, &format!("{}", cy + 1));
+		}
+
+		for cy in 0..givens.height {
+			for cx in 0..givens.width {
+				let center = (left + (cx as f32 + 0.5) * cell, top + (cy as f32 + 0.5) * cell);
+				let given = givens.map[cy][cx];
+				let (state, gray) = match solution {
+					Some(solution) if !given.is_filled() => (solution.map[cy][cx], 0.55),
+					_ => (given, 0.0),
+				};
+				match state {
+					CellState::Blocked => self.shapes.push(Shape::Rect { x: center.0 - cell / 2.0, y: center.1 - cell / 2.0, w: cell, h: cell, gray: 0.3 }),
+					CellState::True(_) => self.text(center, cell * 0.6, gray, "1"),
+					CellState::False(_) => self.text(center, cell * 0.6, gray, "0"),
+					CellState::None => {}
+				}
+			}
+		}
+
+		for cx in 0..=givens.width {
+			let width = if cx == 0 || cx == givens.width {1.5} else {0.5};
+			self.line((left + cx as f32 * cell, top), (left + cx as f32 * cell, top + grid_h), width, 0.0);
+		}
+		for cy in 0..=givens.height {
+			let width = if cy == 0 || cy == givens.height {1.5} else {0.5};
+			self.line((left, top + cy as f32 * cell), (left + grid_w, top + cy as f32 * cell), width, 0.0);
+		}
+
+		for cy in 0..givens.height {
+			for cx in 0..givens.width {
+				let [right, down] = givens.constraints[cy][cx];
+				self.constraint(right, (left + (cx + 1) as f32 * cell, top + (cy as f32 + 0.5) * cell), cell);
+				self.constraint(down, (left + (cx as f32 + 0.5) * cell, top + (cy + 1) as f32 * cell), cell);
+			}
+		}
+	}
+
+	fn constraint(&mut self, constraint: Constraint, center: (f32, f32), cell: f32) {
+		let text = match constraint {
+			Constraint::None => return,
+			Constraint::Equal => "=",
+			Constraint::Opposite => "x",
+		};
+		self.shapes.push(Shape::Circle { center, r: cell * 0.18, fill: 1.0, stroke: 0.0 });
+		self.text(center, cell * 0.3, 0.0, text);
+	}
+}
+
+// spreadsheet style, A to Z, then AA and on
+pub fn column_name(mut x: usize) -> String {
+	let mut name = String::new();
+	loop {
+		name.insert(0, (b'A' + (x % 26) as u8) as char);
+		if x < 26 {
+			break;
+		}
+		x = x / 26 - 1;
+	}
+	name
+}
+
+fn escape_xml(text: &str) -> String {
+	text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn svg_gray(gray: f32) -> String {
+	let v = (gray.clamp(0.0, 1.0) * 255.0).round() as u8;
+	format!("#{v:02x}{v:02x}{v:02x}")
+}
+
+pub fn svg(page: &Page) -> String {
+	let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{PAGE_WIDTH}pt\" height=\"{PAGE_HEIGHT}pt\" viewBox=\"0 0 {PAGE_WIDTH} {PAGE_HEIGHT}\">\n");
+	svg += &format!("<rect width=\"{PAGE_WIDTH}\" height=\"{PAGE_HEIGHT}\" fill=\"#ffffff\"/>\n");
+
+	for shape in &page.shapes {
+		svg += &match shape {
+			Shape::Line { from, to, width, gray } => format!("<line x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\" stroke=\"{}\" stroke-width=\"{width}\" stroke-linecap=\"square\"/>\n", from.0, from.1, to.0, to.1, svg_gray(*gray)),
+			Shape::Rect { x, y, w, h, gray } => format!("<rect x=\"{x:.2}\" y=\"{y:.2}\" width=\"{w:.2}\" height=\"{h:.2}\" fill=\"{}\"/>\n", svg_gray(*gray)),
+			Shape::Circle { center, r, fill, stroke } => format!("<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{r:.2}\" fill=\"{}\" stroke=\"{}\" stroke-width=\"0.3\"/>\n", center.0, center.1, svg_gray(*fill), svg_gray(*stroke)),
+			Shape::Text { center, size, gray, text } => format!("<text x=\"{:.2}\" y=\"{:.2}\" font-family=\"Helvetica, Arial, sans-serif\" font-size=\"{size:.2}\" fill=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>\n", center.0, center.1, svg_gray(*gray), escape_xml(text)),
+		};
+	}
+
+	svg + "</svg>\n"
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::format;
+
+	fn sheet_puzzle(solved: bool) -> SheetPuzzle {
+		let givens = format::from_text("4x4\n.01.\n.0..\n0...\n..1.\n").unwrap().remove(0);
+		let solution = if solved {givens.unique_solution()} else {None};
+		SheetPuzzle { label: "4x4".to_string(), givens, solution }
+	}
+
+	fn texts(page: &Page) -> Vec<&str> {
+		page.shapes.iter().filter_map(|shape| if let Shape::Text { text, .. } = shape {Some(text.as_str())} else {None}).collect()
+	}
+
+	// cells printed in a gray, the coordinates are lighter than both
+	fn cells(page: &Page, in_gray: f32) -> usize {
+		page.shapes.iter().filter(|shape| matches!(shape, Shape::Text { text, gray, .. } if *gray == in_gray && (text == "0" || text == "1"))).count()
+	}
+
+	#[test]
+	fn answers_follow_the_puzzles() {
+		let puzzles = [sheet_puzzle(true), sheet_puzzle(false), sheet_puzzle(true)];
+		assert_eq!(layout("Sheet", &puzzles, 2, false).len(), 2);
+
+		let pages = layout("Sheet", &puzzles, 2, true);
+		assert_eq!(pages.len(), 3);
+		assert!(texts(&pages[2]).contains(&"Sheet - answers"));
+		// the puzzle pages only have the givens, the answers fill in the rest in gray
+		assert_eq!((cells(&pages[0], 0.0), cells(&pages[0], 0.55)), (2 * 5, 0));
+		assert_eq!((cells(&pages[2], 0.0), cells(&pages[2], 0.55)), (2 * 5, 2 * 11));
+	}
+
+	#[test]
+	fn columns_are_named_like_a_spreadsheet() {
+		let names: Vec<String> = [0, 25, 26, 27, 701, 702].into_iter().map(column_name).collect();
+		assert_eq!(names, ["A", "Z", "AA", "AB", "ZZ", "AAA"]);
+	}
+
+	#[test]
+	fn svg_escapes_text() {
+		let svg = svg(&layout("<Tom & Jerry>", &[sheet_puzzle(false)], 1, false)[0]);
+		assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+		assert!(svg.contains("&lt;Tom &amp; Jerry&gt;"));
+	}
+}
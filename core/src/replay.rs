@@ -0,0 +1,363 @@
+use crate::{board::Board, cell_state::CellState, format};
+
+// Recorded games. A replay is the starting board and every move with its time, played back by applying the moves in order.
+// The text format is the mode, the starting board in the text format, one line per move and, for won games, the final time and board:
+//
+//   replay serious
+//   6x6
+//   ..1...
+//   ...
+//   1.53 2 0 1      a cell set to '1', '0' or '.'
+//   2.10 hint 4 5   a hint pointing at a cell
+//   4.00 undo       the last cell change taken back
+//   won 45.32
+//   011010
+//   ...
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Action {
+	Cell(usize, usize, CellState), // x, y, the new state
+	Hint(usize, usize),
+	Undo,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Event {
+	pub time: f32, // seconds since the game started
+	pub action: Action,
+}
+
+#[derive(Clone)]
+pub struct Replay {
+	pub mode: String, // "serious" or "learn"
+	pub puzzle: Board, // the board when the game started
+	pub events: Vec<Event>,
+	pub finish: Option<(f32, Board)>, // time and board of a won game
+	pub start: f32, // clock time the recording started at, kept by the front end and not saved
+}
+
+impl Replay {
+	pub fn new(mode: &str, puzzle: &Board) -> Self {
+		Self { mode: mode.to_string(), puzzle: puzzle.clone(), events: Vec::new(), finish: None, start: 0.0 }
+	}
+
+	pub fn record(&mut self, time: f32, action: Action) {
+		self.events.push(Event { time, action });
+	}
+
+	// every cell that differs between the boards, as made by the player
+	pub fn record_changes(&mut self, time: f32, before: &[Vec<CellState>], after: &Board) {
+		for (y, (old, new)) in before.iter().zip(&after.map).enumerate() {
+			for (x, (old, new)) in old.iter().zip(new).enumerate() {
+				if old != new {
+					self.record(time, Action::Cell(x, y, *new));
+				}
+			}
+		}
+	}
+
+	pub fn finish(&mut self, time: f32, board: &Board) {
+		self.finish = Some((time, board.clone()));
+	}
+
+	pub fn duration(&self) -> f32 {
+		match &self.finish {
+			Some((time, _)) => *time,
+			None => self.events.last().map(|e| e.time).unwrap_or(0.0),
+		}
+	}
+
+	// the cell an undo would restore and its previous state, after the events so far
+	pub fn undo_target(&self) -> Option<(usize, usize, CellState)> {
+		let mut map = self.puzzle.map.clone();
+		let mut stack = Vec::new();
+		for event in &self.events {
+			match event.action {
+				Action::Cell(x, y, state) => {
+					stack.push((x, y, map[y][x]));
+					map[y][x] = state;
+				}
+				Action::Undo => {
+					if let Some((x, y, state)) = stack.pop() {
+						map[y][x] = state;
+					}
+				}
+				Action::Hint(..) => {}
+			}
+		}
+		stack.pop()
+	}
+
+	// the board as it was at a time of the game, with the hint shown then
+	pub fn board_at(&self, time: f32) -> Board {
+		let mut board = self.puzzle.clone();
+		self.play(time, |event, map| {
+			board.map = map.to_vec();
+			board.hint = match event.action {
+				Action::Hint(x, y) => Some((x, y)),
+				_ => None,
+			};
+		});
+		board.verify_board();
+		board
+	}
+
+	// calls back after every event up to the time with the cells as they are after it
+	fn play<F: FnMut(&Event, &[Vec<CellState>])>(&self, time: f32, mut f: F) {
+		let mut map = self.puzzle.map.clone();
+		let mut stack = Vec::new();
+		for event in self.events.iter().take_while(|e| e.time <= time) {
+			match event.action {
+				Action::Cell(x, y, state) => {
+					stack.push((x, y, map[y][x]));
+					map[y][x] = state;
+				}
+				Action::Undo => {
+					if let Some((x, y, state)) = stack.pop() {
+						map[y][x] = state;
+					}
+				}
+				Action::Hint(..) => {}
+			}
+			f(event, &map);
+		}
+	}
+
+	// the same givens and markers, e.g. a pack puzzle played again
+	pub fn is_same_puzzle(&self, board: &Board) -> bool {
+		format::to_text(&self.puzzle) == format::to_text(board)
+	}
+
+	// cells the player has filled in, not counting the givens
+	pub fn filled(map: &[Vec<CellState>]) -> usize {
+		map.iter().flatten().filter(|cell| cell.is_filled() && !cell.is_locked()).count()
+	}
+
+	pub fn filled_at(&self, time: f32) -> usize {
+		let mut filled = 0;
+		self.play(time, |_, map| filled = Self::filled(map));
+		filled
+	}
+
+	// when the game first had this many cells filled in, the finish time if it never had
+	pub fn time_to_fill(&self, filled: usize) -> f32 {
+		let mut reached = None;
+		self.play(self.duration(), |event, map| {
+			if reached.is_none() && Self::filled(map) >= filled {
+				reached = Some(event.time);
+			}
+		});
+		reached.unwrap_or(self.duration())
+	}
+
+	// racing this game: the cells it had filled in `passed` seconds in, the cells it filled in all in all,
+	// and how many seconds a game with `filled` cells is behind it, negative when ahead. None before the first cell
+	pub fn race(&self, passed: f32, filled: usize, finished: bool) -> (usize, usize, Option<f32>) {
+		let total = self.finish.as_ref().map(|(_, won)| Self::filled(&won.map)).unwrap_or(1).max(1);
+		let delta = if finished {Some(passed - self.duration())} else if filled > 0 {Some(passed - self.time_to_fill(filled))} else {None};
+		(self.filled_at(passed), total, delta)
+	}
+
+	// playing every move has to end in the recorded winning board
+	pub fn verify(&self) -> Result<(), String> {
+		let Some((time, won)) = &self.finish else {
+			return Err("the game wasn't finished".to_string());
+		};
+		if let Some(event) = self.events.iter().find(|e| e.time > *time) {
+			return Err(format!("a move at {:.2}s comes after the finish at {time:.2}s", event.time));
+		}
+		for event in &self.events {
+			if let Action::Cell(x, y, _) = event.action {
+				if self.puzzle.map[y][x].is_locked() {
+					return Err(format!("a move at {:.2}s changes a given cell", event.time));
+				}
+			}
+		}
+
+		let board = self.board_at(*time);
+		if board.map != won.map {
+			return Err("the moves don't lead to the recorded board".to_string());
+		}
+		if !board.is_won {
+			return Err("the recorded board isn't solved".to_string());
+		}
+		Ok(())
+	}
+
+	pub fn to_text(&self) -> String {
+		let mut text = format!("replay {}\n{}", self.mode, format::to_text(&self.puzzle));
+		for event in &self.events {
+			text += &match event.action {
+				Action::Cell(x, y, state) => format!("{:.2} {x} {y} {}\n", event.time, format::cell_char(state)),
+				Action::Hint(x, y) => format!("{:.2} hint {x} {y}\n", event.time),
+				Action::Undo => format!("{:.2} undo\n", event.time),
+			};
+		}
+		if let Some((time, board)) = &self.finish {
+			text += &format!("won {time:.2}\n");
+			for row in format::rows(board) {
+				text += &row;
+				text += "\n";
+			}
+		}
+		text
+	}
+
+	pub fn from_text(text: &str) -> Result<Self, String> {
+		let lines: Vec<&str> = text.lines().collect();
+		let mode = lines.first().and_then(|line| line.strip_prefix("replay ")).ok_or("not a replay, the first line should be \"replay <mode>\"")?.trim();
+		let header = lines.get(1).ok_or("missing the board")?;
+		let (width, height, rules) = format::parse_header(header).map_err(|e| format!("line 2: {e}"))?;
+		let n = format::row_lines(height, rules);
+		let rows = lines.get(2..2 + n).ok_or("the board is cut short")?;
+		let puzzle = format::parse_rows(width, height, rules, rows).map_err(|e| format!("board: {e}"))?;
+
+		let mut replay = Replay::new(mode, &puzzle);
+		let mut i = 2 + n;
+		while i < lines.len() {
+			let line = lines[i].trim();
+			let words: Vec<&str> = line.split_whitespace().collect();
+			let error = |e: &str| format!("line {}: {e}", i + 1);
+			let x = |word: &str| word.parse::<usize>().ok().filter(|x| *x < width).ok_or(error(&format!("\"{word}\" is not a column of the board")));
+			let y = |word: &str| word.parse::<usize>().ok().filter(|y| *y < height).ok_or(error(&format!("\"{word}\" is not a row of the board")));
+
+			match words.as_slice() {
+				[] => {}
+				["won", time] => {
+					let time = time.parse().map_err(|_| error(&format!("\"{time}\" is not a time")))?;
+					let rows = lines.get(i + 1..i + 1 + n).ok_or(error("the final board is cut short"))?;
+					let mut board = format::parse_rows(width, height, rules, rows).map_err(|e| error(&format!("final board: {e}")))?;
+					// only the givens of the puzzle stay locked, the rest was filled in by the player
+					for (row, puzzle_row) in board.map.iter_mut().zip(&replay.puzzle.map) {
+						for (cell, given) in row.iter_mut().zip(puzzle_row) {
+							if !given.is_locked() {
+								*cell = unlocked(*cell);
+							}
+						}
+					}
+					replay.finish(time, &board);
+					i += n;
+				}
+				[time, rest @ ..] => {
+					let time = time.parse().map_err(|_| error(&format!("\"{time}\" is not a time")))?;
+					let action = match rest {
+						["undo"] => Action::Undo,
+						["hint", cx, cy] => Action::Hint(x(cx)?, y(cy)?),
+						[cx, cy, cell] => {
+							let state = format::parse_cell(cell.chars().next().unwrap_or(' ')).filter(|s| *s != CellState::Blocked && cell.len() == 1).map(unlocked);
+							Action::Cell(x(cx)?, y(cy)?, state.ok_or(error(&format!("unknown cell \"{cell}\"")))?)
+						}
+						_ => return Err(error(&format!("unknown move \"{line}\""))),
+					};
+					replay.record(time, action);
+				}
+			}
+			i += 1;
+		}
+
+		Ok(replay)
+	}
+}
+
+// a cell as placed by the player, the text format reads every cell as a given
+fn unlocked(cell: CellState) -> CellState {
+	match cell {
+		CellState::True(_) => CellState::True(false),
+		CellState::False(_) => CellState::False(false),
+		other => other,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const PUZZLE: &str = "4x4\n.01.\n.0..\n0...\n..1.\n";
+	const SOLUTION: [&str; 4] = ["1010", "1001", "0101", "0110"];
+
+	// a won game: a wrong cell taken back, a hint and then every free cell a second apart
+	fn game() -> Replay {
+		let puzzle = format::from_text(PUZZLE).unwrap().remove(0);
+		let mut replay = Replay::new("serious", &puzzle);
+		replay.record(0.5, Action::Cell(0, 0, CellState::False(false)));
+		replay.record(0.8, Action::Undo);
+		replay.record(0.9, Action::Hint(0, 0));
+		let mut time = 0.0;
+		for (y, row) in SOLUTION.iter().enumerate() {
+			for (x, c) in row.chars().enumerate() {
+				if !puzzle.map[y][x].is_locked() {
+					time += 1.0;
+					replay.record(time, Action::Cell(x, y, CellState::from_bool(c == '1')));
+				}
+			}
+		}
+		let won = replay.board_at(time);
+		replay.finish(time + 0.5, &won);
+		replay
+	}
+
+	#[test]
+	fn loaded_replays_play_like_recorded_ones() {
+		let recorded = game();
+		let loaded = Replay::from_text(&recorded.to_text()).unwrap();
+		assert_eq!(loaded.to_text(), recorded.to_text());
+		assert_eq!(loaded.events.len(), recorded.events.len());
+
+		for time in [0.0, 0.6, 0.9, 3.0, 7.5, 11.0, 20.0] {
+			assert_eq!(loaded.filled_at(time), recorded.filled_at(time), "at {time}s");
+		}
+		assert_eq!((loaded.filled_at(0.6), loaded.filled_at(0.9), loaded.filled_at(3.0), loaded.filled_at(11.0)), (1, 0, 3, 11));
+		assert_eq!(loaded.time_to_fill(3), 3.0);
+		assert_eq!(loaded.time_to_fill(11), 11.0);
+		assert_eq!(loaded.duration(), 11.5);
+		assert_eq!(loaded.board_at(1.0).hint, None);
+		assert_eq!(loaded.board_at(0.9).hint, Some((0, 0)));
+		assert!(loaded.verify().is_ok());
+	}
+
+	#[test]
+	fn only_givens_are_locked_after_loading() {
+		let loaded = Replay::from_text(&game().to_text()).unwrap();
+		let (_, won) = loaded.finish.as_ref().unwrap();
+		assert!(won.map[0][1].is_locked());
+		assert!(!won.map[0][0].is_locked() && won.map[0][0].is_filled());
+		assert!(loaded.events.iter().all(|e| !matches!(e.action, Action::Cell(_, _, cell) if cell.is_locked())));
+		assert!(!loaded.board_at(5.0).map[0][0].is_locked());
+	}
+
+	#[test]
+	fn races_a_loaded_replay() {
+		let ghost = Replay::from_text(&game().to_text()).unwrap();
+		assert_eq!(ghost.race(0.2, 0, false), (0, 11, None));
+		assert_eq!(ghost.race(3.0, 2, false), (3, 11, Some(1.0)));
+		assert_eq!(ghost.race(1.5, 4, false), (1, 11, Some(-2.5)));
+		assert_eq!(ghost.race(10.0, 11, true), (10, 11, Some(-1.5)));
+	}
+
+	#[test]
+	fn broken_games_fail_verification() {
+		let mut unfinished = game();
+		unfinished.finish = None;
+		assert!(unfinished.verify().is_err());
+
+		let mut given_changed = game();
+		given_changed.events.insert(0, Event { time: 0.1, action: Action::Cell(1, 0, CellState::True(false)) });
+		assert!(given_changed.verify().err().unwrap().contains("given"));
+
+		let text = game().to_text().replace("won 11.50", "won 5.00");
+		assert!(Replay::from_text(&text).unwrap().verify().err().unwrap().contains("after the finish"));
+
+		let mut wrong_board = game();
+		wrong_board.finish.as_mut().unwrap().1.map[3][3] = CellState::None;
+		assert!(wrong_board.verify().err().unwrap().contains("don't lead"));
+	}
+
+	#[test]
+	fn broken_text_is_rejected() {
+		assert!(Replay::from_text("6x6\n").is_err());
+		let text = game().to_text();
+		assert!(Replay::from_text(&text.replace("0.80 undo", "0.80 redo")).err().unwrap().contains("unknown move"));
+		assert!(Replay::from_text(&text.replace("0.90 hint 0 0", "0.90 hint 9 0")).err().unwrap().contains("column"));
+		assert!(Replay::from_text(&text.replace("1.00 0 0 1", "1.00 0 0 #")).err().unwrap().contains("unknown cell"));
+	}
+}
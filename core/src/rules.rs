@@ -0,0 +1,53 @@
+use nanoserde::{DeBin, SerBin};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, SerBin, DeBin)]
+pub enum Mask {
+	#[default]
+	None,
+	Random,
+	Symmetric, // blocked cells mirrored through the center of the board
+}
+
+impl Mask {
+	pub fn as_str(&self) -> &str {
+		match self {
+			Mask::None => "No holes",
+			Mask::Random => "Holes",
+			Mask::Symmetric => "Sym. holes",
+		}
+	}
+
+	pub fn next(&self) -> Self {
+		match self {
+			Mask::None => Mask::Random,
+			Mask::Random => Mask::Symmetric,
+			Mask::Symmetric => Mask::None,
+		}
+	}
+}
+
+// which variant of the puzzle a board is played with
+#[derive(Copy, Clone, Debug, Default, PartialEq, SerBin, DeBin)]
+pub struct Rules {
+	pub constraints: bool, // Binairo+ - "=" and "×" markers between some neighbouring cells
+	pub odd_sizes: bool, // allows odd dimensions, where a line may hold one more of a color than the other
+	pub mask: Mask, // blocked cells splitting lines into segments
+	pub wrap: bool, // the no-triplet rule continues over the edges, as if the board was a torus
+}
+
+impl Rules {
+	// short suffix shown next to the board size
+	pub fn label(&self) -> String {
+		let mut label = String::new();
+		if self.constraints {
+			label += " +";
+		}
+		if self.mask != Mask::None {
+			label += " #";
+		}
+		if self.wrap {
+			label += " ~";
+		}
+		label
+	}
+}
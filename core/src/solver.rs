@@ -0,0 +1,152 @@
+use nanoserde::{DeJson, SerJson};
+
+use crate::{board::Board, cell_state::CellState};
+
+// how hard a puzzle is for a human, judged by the deductions it takes
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd, SerJson, DeJson)]
+pub enum Difficulty {
+	#[default]
+	Easy, // neighbour patterns alone solve it
+	Medium, // needs counting cells of a line
+	Hard, // needs counting and long chains of deductions
+	Expert, // can't be solved without trying out values
+}
+
+impl Difficulty {
+	pub fn as_str(&self) -> &str {
+		match self {
+			Difficulty::Easy => "easy",
+			Difficulty::Medium => "medium",
+			Difficulty::Hard => "hard",
+			Difficulty::Expert => "expert",
+		}
+	}
+
+	pub fn parse(s: &str) -> Option<Self> {
+		[Difficulty::Easy, Difficulty::Medium, Difficulty::Hard, Difficulty::Expert].into_iter().find(|d| d.as_str() == s)
+	}
+}
+
+impl Board {
+	// applies every deduction until nothing changes
+	pub fn propagate(&mut self) {
+		while self.surround_doubles() | self.separate_triples() | self.fill_rows() | self.apply_constraints() | self.extend_equal_pairs() {}
+	}
+
+	// completions of the board, stopping after `limit` of them. Deduces as far as possible and only guesses when stuck
+	pub fn solutions(&self, limit: usize) -> Vec<Board> {
+		let mut found = Vec::new();
+		self.clone().search(limit, &mut found);
+		found
+	}
+
+	fn search(mut self, limit: usize, found: &mut Vec<Board>) {
+		self.propagate();
+		self.verify_board();
+		if !self.is_valid {
+			return;
+		}
+		if self.is_won {
+			found.push(self);
+			return;
+		}
+
+		let Some((x, y)) = (0..self.height).flat_map(|y| (0..self.width).map(move |x| (x, y))).find(|(x, y)| self.map[*y][*x] == CellState::None) else { return };
+		for b in [false, true] {
+			let mut guess = self.clone();
+			guess.map[y][x] = CellState::from_bool(b);
+			guess.search(limit, found);
+			if found.len() >= limit {
+				return;
+			}
+		}
+	}
+
+	// the only solution of the board, if there is exactly one
+	pub fn unique_solution(&self) -> Option<Board> {
+		let mut solutions = self.solutions(2);
+		if solutions.len() == 1 {
+			solutions.pop()
+		}
+		else {
+			None
+		}
+	}
+
+	// None when the puzzle doesn't have exactly one solution
+	pub fn grade(&self) -> Option<Difficulty> {
+		self.unique_solution()?;
+
+		// neighbour patterns are tried first, counting only when they are stuck. The more often counting is needed the harder the puzzle
+		let mut board = self.clone();
+		let mut counts = 0;
+		loop {
+			while board.surround_doubles() | board.separate_triples() | board.apply_constraints() | board.extend_equal_pairs() {}
+			if !board.fill_rows() {
+				break;
+			}
+			counts += 1;
+		}
+
+		if board.has_nones() {
+			Some(Difficulty::Expert)
+		}
+		else if counts == 0 {
+			Some(Difficulty::Easy)
+		}
+		else if counts <= (self.width + self.height) / 4 {
+			Some(Difficulty::Medium)
+		}
+		else {
+			Some(Difficulty::Hard)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::format;
+
+	const PUZZLE: &str = "6x6\n.0....\n.....0\n.0.1.0\n..0...\n...1..\n1.0.0.\n";
+	const SOLUTION: &str = "6x6\n001011\n110010\n101100\n010011\n001101\n110100\n";
+
+	fn board(text: &str) -> Board {
+		format::from_text(text).unwrap().remove(0)
+	}
+
+	#[test]
+	fn solves_a_known_puzzle() {
+		let solution = board(PUZZLE).unique_solution().expect("the puzzle has one solution");
+		assert!(solution.is_won);
+		assert_eq!(format::to_text(&solution), SOLUTION);
+		assert_eq!(board(PUZZLE).grade(), Some(Difficulty::Medium));
+	}
+
+	#[test]
+	fn propagates_deductions() {
+		let mut board = board("4x4\n11..\n....\n....\n....\n");
+		board.propagate();
+		assert_eq!(format::rows(&board)[0], "1100");
+	}
+
+	#[test]
+	fn finds_ambiguous_and_broken_puzzles() {
+		let empty = board("4x4\n....\n....\n....\n....\n");
+		assert_eq!(empty.solutions(2).len(), 2);
+		assert!(empty.unique_solution().is_none());
+		assert_eq!(empty.grade(), None);
+
+		let broken = board("4x4\n111.\n....\n....\n....\n");
+		assert!(broken.solutions(1).is_empty());
+		assert_eq!(broken.grade(), None);
+	}
+
+	#[test]
+	fn difficulty_names_round_trip() {
+		for difficulty in [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard, Difficulty::Expert] {
+			assert_eq!(Difficulty::parse(difficulty.as_str()), Some(difficulty));
+		}
+		assert_eq!(Difficulty::parse("impossible"), None);
+	}
+}
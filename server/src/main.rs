@@ -0,0 +1,73 @@
+use std::{env, io::{self, Write}, process::ExitCode, thread, time::{SystemTime, UNIX_EPOCH}};
+
+use takuzu_core::{net::{Mode, Server, PORT}, rules::Rules};
+
+const USAGE: &str = "usage:
+  takuzu-server [--port P] [--size N|WxH] [--plus] [--wrap] [--coop]
+
+Two players join from the game's lobby and race on the same puzzle, or with --coop up to four fill in one board together.
+--port 0 picks a free port.";
+
+struct Options {
+	port: u16,
+	mode: Mode,
+	width: usize,
+	height: usize,
+	rules: Rules,
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+	let mut options = Options { port: PORT, mode: Mode::Race, width: 8, height: 8, rules: Rules::default() };
+
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+		match arg.as_str() {
+			"--port" => options.port = value()?.parse().map_err(|_| "--port needs a number")?,
+			"--size" => {
+				let size = value()?;
+				let (w, h) = size.split_once('x').unwrap_or((size, size));
+				options.width = w.parse().map_err(|_| format!("\"{w}\" is not a valid width"))?;
+				options.height = h.parse().map_err(|_| format!("\"{h}\" is not a valid height"))?;
+				if options.width < 2 || options.height < 2 {
+					return Err(format!("a {}x{} board is too small, both sides need at least 2 cells", options.width, options.height));
+				}
+			}
+			"--plus" => options.rules.constraints = true,
+			"--wrap" => options.rules.wrap = true,
+			"--coop" => options.mode = Mode::Coop,
+			"--help" => return Err(USAGE.to_string()),
+			_ => return Err(format!("unknown option {arg}\n\n{USAGE}")),
+		}
+	}
+
+	options.rules.odd_sizes = options.width % 2 == 1 || options.height % 2 == 1;
+	Ok(options)
+}
+
+fn main() -> ExitCode {
+	let args: Vec<String> = env::args().skip(1).collect();
+	let options = match parse_options(&args) {
+		Ok(options) => options,
+		Err(e) => {
+			eprintln!("{e}");
+			return ExitCode::FAILURE;
+		}
+	};
+	quad_rand::srand(SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64);
+
+	let server = match Server::start(&format!("0.0.0.0:{}", options.port), options.mode, options.width, options.height, options.rules) {
+		Ok(server) => server,
+		Err(e) => {
+			eprintln!("error: {e}");
+			return ExitCode::FAILURE;
+		}
+	};
+	// the port is the first line, for scripts that asked for any free one
+	println!("listening on port {}", server.address().port());
+	let _ = io::stdout().flush();
+
+	loop {
+		thread::park();
+	}
+}
@@ -0,0 +1,47 @@
+use std::{io::{BufRead, BufReader}, process::{Child, Command, Stdio}, time::Duration};
+
+use takuzu_core::net::{Client, Message};
+
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+// the server binary on a free port, killed when the test ends
+pub struct Server(Child, u16);
+
+impl Server {
+	pub fn start(args: &[&str]) -> Self {
+		let mut child = Command::new(env!("CARGO_BIN_EXE_takuzu-server"))
+			.args(["--port", "0"])
+			.args(args)
+			.stdout(Stdio::piped())
+			.spawn()
+			.expect("the server starts");
+		let mut line = String::new();
+		BufReader::new(child.stdout.as_mut().unwrap()).read_line(&mut line).unwrap();
+		let port = line.trim().strip_prefix("listening on port ").and_then(|port| port.parse().ok()).expect("the server prints its port");
+		Server(child, port)
+	}
+
+	pub fn address(&self) -> String {
+		format!("127.0.0.1:{}", self.1)
+	}
+}
+
+impl Drop for Server {
+	fn drop(&mut self) {
+		let _ = self.0.kill();
+		let _ = self.0.wait();
+	}
+}
+
+pub fn recv(client: &Client) -> Message {
+	client.recv_timeout(TIMEOUT).expect("a message from the server")
+}
+
+// waits for the message, skipping the ones before it
+pub fn expect<T, F: Fn(Message) -> Option<T>>(client: &Client, f: F) -> T {
+	loop {
+		if let Some(found) = f(recv(client)) {
+			return found;
+		}
+	}
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use std::time::Duration;
+
+use takuzu_core::{board::Board, cell_state::CellState, format, net::{Client, Message, Mode}};
+use common::{Server, recv, expect};
+
+// everything the server sends until it goes quiet
+fn drain(client: &Client) -> Vec<Message> {
+	let mut messages = Vec::new();
+	while let Some(message) = client.recv_timeout(Duration::from_millis(500)) {
+		messages.push(message);
+	}
+	messages
+}
+
+// the last word on a cell among the messages: its state, owner and version
+fn last_cell(messages: &[Message], x: usize, y: usize) -> Option<(CellState, Option<usize>, u32)> {
+	messages.iter().rev().find_map(|m| match m {
+		Message::Cell(cx, cy, cell, owner, version) if (*cx, *cy) == (x, y) => Some((*cell, *owner, *version)),
+		_ => None,
+	})
+}
+
+fn join(server: &Server, name: &str) -> (Client, usize) {
+	let client = Client::connect(&server.address(), name).unwrap();
+	assert!(matches!(recv(&client), Message::Mode(Mode::Coop)));
+	let id = expect(&client, |m| if let Message::Welcome(id) = m {Some(id)} else {None});
+	(client, id)
+}
+
+fn free_cells(puzzle: &Board) -> Vec<(usize, usize)> {
+	(0..puzzle.height).flat_map(|y| (0..puzzle.width).map(move |x| (x, y)))
+		.filter(|(x, y)| puzzle.map[*y][*x] == CellState::None)
+		.collect()
+}
+
+#[test]
+fn scripted_clients_share_a_board() {
+	let server = Server::start(&["--size", "6x6", "--coop"]);
+	let (alice, a) = join(&server, "alice");
+	let puzzle = expect(&alice, |m| if let Message::Puzzle(board) = m {Some(board)} else {None});
+	let (bob, b) = join(&server, "bob");
+	assert_eq!(expect(&bob, |m| if let Message::Player(id, name) = m {Some((id, name))} else {None}), (a, "alice".to_string()));
+	assert_eq!(expect(&alice, |m| if let Message::Player(id, name) = m {Some((id, name))} else {None}), (b, "bob".to_string()));
+	let bobs = expect(&bob, |m| if let Message::Puzzle(board) = m {Some(board)} else {None});
+	assert_eq!(format::to_text(&puzzle), format::to_text(&bobs));
+
+	// both fill in the same cell at once, only one of them gets it and both end up seeing the same
+	let free = free_cells(&puzzle);
+	let (x, y) = free[0];
+	alice.send(&Message::Set(x, y, CellState::True(false), 0)).unwrap();
+	bob.send(&Message::Set(x, y, CellState::False(false), 0)).unwrap();
+	let seen = [last_cell(&drain(&alice), x, y), last_cell(&drain(&bob), x, y)];
+	assert_eq!(seen[0], seen[1]);
+	let (cell, owner, version) = seen[0].expect("the cell was sent back");
+	assert_eq!(version, 1);
+	match owner {
+		Some(id) if id == a => assert_eq!(cell, CellState::True(false)),
+		Some(id) if id == b => assert_eq!(cell, CellState::False(false)),
+		_ => panic!("the cell belongs to one of the players"),
+	}
+
+	// a move on an old version of the cell is corrected
+	bob.send(&Message::Set(x, y, CellState::None, 0)).unwrap();
+	assert_eq!(expect(&bob, |m| if let Message::Cell(..) = m {last_cell(&[m], x, y)} else {None}), (cell, owner, 1));
+
+	// givens can't be changed
+	let (gx, gy) = (0..puzzle.height).flat_map(|y| (0..puzzle.width).map(move |x| (x, y))).find(|(x, y)| puzzle.map[*y][*x].is_locked()).expect("the puzzle has givens");
+	alice.send(&Message::Set(gx, gy, CellState::None, 0)).unwrap();
+	assert!(matches!(recv(&alice), Message::Rejected(_)));
+
+	// a late player gets the board as it is
+	let (carol, c) = join(&server, "carol");
+	let messages = drain(&carol);
+	assert_eq!(messages.iter().filter(|m| matches!(m, Message::Player(..))).count(), 2);
+	assert!(messages.iter().any(|m| matches!(m, Message::Puzzle(board) if format::to_text(board) == format::to_text(&puzzle))));
+	assert_eq!(last_cell(&messages, x, y), Some((cell, owner, 1)));
+	drain(&alice);
+	drain(&bob);
+
+	// four is the most, the fifth is turned away
+	let (dave, _) = join(&server, "dave");
+	let eve = Client::connect(&server.address(), "eve").unwrap();
+	assert!(matches!(recv(&eve), Message::Mode(Mode::Coop)));
+	assert!(matches!(recv(&eve), Message::Full));
+	drop(dave);
+	for client in [&alice, &bob, &carol] {
+		expect(client, |m| if let Message::Gone(_) = m {Some(())} else {None});
+	}
+
+	// carol fills in the rest of the solution and everyone gets the result
+	let solution = puzzle.unique_solution().expect("generated puzzles have one solution");
+	for (fx, fy) in free {
+		let version = if (fx, fy) == (x, y) {1} else {0};
+		carol.send(&Message::Set(fx, fy, solution.map[fy][fx], version)).unwrap();
+	}
+	for client in [&alice, &bob, &carol] {
+		assert_eq!(expect(client, |m| if let Message::Result(winner, _) = m {Some(winner)} else {None}), c);
+	}
+
+	drop(carol);
+	assert_eq!(expect(&alice, |m| if let Message::Gone(id) = m {Some(id)} else {None}), c);
+}
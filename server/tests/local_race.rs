@@ -0,0 +1,52 @@
+mod common;
+
+use common::{Server, recv, expect};
+use takuzu_core::{format, net::{Client, Message, Mode}};
+
+#[test]
+fn two_clients_race() {
+	let server = Server::start(&["--size", "6x6"]);
+	let alice = Client::connect(&server.address(), "alice").unwrap();
+	assert!(matches!(recv(&alice), Message::Mode(Mode::Race)));
+	assert!(matches!(recv(&alice), Message::Welcome(0)));
+	let bob = Client::connect(&server.address(), "bob").unwrap();
+
+	assert_eq!(expect(&alice, |m| if let Message::Opponent(name) = m {Some(name)} else {None}), "bob");
+	assert_eq!(expect(&bob, |m| if let Message::Opponent(name) = m {Some(name)} else {None}), "alice");
+	let puzzle = expect(&alice, |m| if let Message::Puzzle(board) = m {Some(board)} else {None});
+	let bobs = expect(&bob, |m| if let Message::Puzzle(board) = m {Some(board)} else {None});
+	assert_eq!(format::to_text(&puzzle), format::to_text(&bobs));
+	assert_eq!((puzzle.width, puzzle.height), (6, 6));
+
+	// progress goes to the other player only
+	alice.send(&Message::Progress(3)).unwrap();
+	assert!(matches!(recv(&bob), Message::Progress(3)));
+
+	// the puzzle as it was handed out isn't solved
+	bob.send(&Message::Finish(bobs.clone())).unwrap();
+	assert!(matches!(recv(&bob), Message::Rejected(_)));
+
+	let solution = puzzle.unique_solution().expect("generated puzzles have one solution");
+	alice.send(&Message::Finish(solution.clone())).unwrap();
+	for client in [&alice, &bob] {
+		assert!(matches!(recv(client), Message::Result(0, time) if time >= 0.0));
+	}
+
+	// a late finish doesn't change the result
+	bob.send(&Message::Finish(solution)).unwrap();
+
+	alice.send(&Message::Rematch).unwrap();
+	assert!(matches!(recv(&bob), Message::Rematch));
+	bob.send(&Message::Rematch).unwrap();
+	for client in [&alice, &bob] {
+		expect(client, |m| if let Message::Puzzle(board) = m {Some(board)} else {None});
+	}
+
+	// a third player is turned away
+	let carol = Client::connect(&server.address(), "carol").unwrap();
+	assert!(matches!(recv(&carol), Message::Mode(Mode::Race)));
+	assert!(matches!(recv(&carol), Message::Full));
+
+	drop(bob);
+	expect(&alice, |m| if let Message::Left = m {Some(())} else {None});
+}
@@ -1,10 +1,12 @@
-use std::{fs::{File, self}, io::{Read, Write}, thread, sync::mpsc::{Receiver, Sender, channel}, time::Instant};
+use std::{fs::{File, self}, io::{Read, Write}, thread, sync::{Arc, atomic::{AtomicBool, Ordering}, mpsc::{Receiver, Sender, TryRecvError, channel}}, time::{Instant, SystemTime, UNIX_EPOCH}};
 use pollster::FutureExt;
 
 use macroquad::{prelude::*, miniquad::{BlendState, Equation, BlendFactor, BlendValue}, audio::{Sound, load_sound_from_bytes, play_sound, PlaySoundParams, set_sound_volume, play_sound_once}};
-use nanoserde::{DeBin, SerBin};
+use nanoserde::{DeBin, DeBinErr, SerBin};
 
-use crate::{MUSIC, SFX, SFX_VOLUMES, MUSIC_LENGTHS, cell_state::CellState, state::GameMode, board::Board, BACKGROUND_FACTOR};
+use takuzu_core::{pack::{Pack, LoadedPack, PackPuzzle}, print::{self, SheetPuzzle}, pdf, replay::Replay, net::PORT};
+
+use crate::{MUSIC, SFX, SFX_VOLUMES, MUSIC_LENGTHS, STARTER_PACK, CAMPAIGN, POPUP_EDGE_COL, state::GameMode, pool::{Pool, Kind}, board::{Board, BoardUi, Phase}, ui::draw_centered_text, rules::Rules, BACKGROUND_FACTOR};
 
 
 pub struct Assets {
@@ -21,11 +23,14 @@ pub struct Assets {
 	pub sfx: Option<Vec<Sound>>,
 	pub assets_receiver: Receiver<(Vec<Sound>, Vec<Sound>)>,
 
-	pub receiver: Receiver<(Vec<Vec<CellState>>, usize, f32)>, // map, id, time
-	pub sender: Sender<(usize, GameMode, usize)>,
+	pub receiver: Receiver<Generated>,
+	pub sender: Sender<(usize, usize, Rules, GameMode, usize, CancelToken)>,
+	pub orders: Vec<(usize, CancelToken)>, // boards ordered from the generator and not yet received
 
 	pub next_board_id: usize,
 
+	pub campaign: Vec<PackPuzzle>, // levels in the order they unlock
+
 	pub background_render_target: RenderTarget,
 }
 
@@ -36,56 +41,79 @@ impl Assets {
 		let mut materials = Vec::new();
 		let paths = fs::read_dir("src/shaders").unwrap();
 
-		for path in paths {
-			match path {
-				Ok(entry) => {
-					let frag = fs::read_to_string(entry.path()).unwrap();
-					materials.push(load_material(
-						include_str!("vertex.vert"), 
-						frag.as_str(), 
-						MaterialParams {
-							uniforms: vec![
-								("time".to_string(), UniformType::Float1),
-								("resolution".to_string(), UniformType::Float2),
-								("alpha".to_string(), UniformType::Float1),
-							],
-							pipeline_params: PipelineParams {
-								alpha_blend: Some(BlendState::new(
-									Equation::Add,
-									BlendFactor::Value(BlendValue::SourceAlpha),
-									BlendFactor::OneMinusValue(BlendValue::SourceAlpha)
-								)),
-								color_blend: Some(BlendState::new(
-									Equation::Add,
-									BlendFactor::Value(BlendValue::SourceAlpha),
-									BlendFactor::OneMinusValue(BlendValue::SourceAlpha)
-								)),
-								..Default::default()
-							},
-							..Default::default()
-						}).unwrap())
-				}
-				Err(_) => {}
-			}
+		for entry in paths.flatten() {
+			let frag = fs::read_to_string(entry.path()).unwrap();
+			materials.push(load_material(
+				include_str!("vertex.vert"), 
+				frag.as_str(), 
+				MaterialParams {
+					uniforms: vec![
+						("time".to_string(), UniformType::Float1),
+						("resolution".to_string(), UniformType::Float2),
+						("alpha".to_string(), UniformType::Float1),
+					],
+					pipeline_params: PipelineParams {
+						alpha_blend: Some(BlendState::new(
+							Equation::Add,
+							BlendFactor::Value(BlendValue::SourceAlpha),
+							BlendFactor::OneMinusValue(BlendValue::SourceAlpha)
+						)),
+						color_blend: Some(BlendState::new(
+							Equation::Add,
+							BlendFactor::Value(BlendValue::SourceAlpha),
+							BlendFactor::OneMinusValue(BlendValue::SourceAlpha)
+						)),
+						..Default::default()
+					},
+					..Default::default()
+				}).unwrap())
 		}
 
 
-		// map, board_id
-		let (map_sender, map_receiver) = channel::<(Vec<Vec<CellState>>, usize, f32)>();
+		let (map_sender, map_receiver) = channel::<Generated>();
 
-		// map_size, game_mode, board_id
-		let (order_sender, order_receiver) = channel::<(usize, GameMode, usize)>();
+		// map_width, map_height, rules, game_mode, board_id, cancellation
+		let (order_sender, order_receiver) = channel::<(usize, usize, Rules, GameMode, usize, CancelToken)>();
 
 		thread::spawn(move || {
+			let mut pool = Pool::load();
+			let mut waiting = None;
 			loop {
-				let (size, mode, id) = order_receiver.recv().unwrap();
+				// orders come first, the pool is topped up a board at a time while there are none and stops for the next one
+				let order = match waiting.take() {
+					Some(order) => order,
+					None => match order_receiver.try_recv() {
+						Ok(order) => order,
+						Err(TryRecvError::Empty) if pool.fill_one(&mut |_, _| {
+							waiting = waiting.take().or_else(|| order_receiver.try_recv().ok());
+							waiting.is_none()
+						}) => continue,
+						Err(_) => order_receiver.recv().unwrap(),
+					},
+				};
+				let (width, height, rules, mode, id, cancel) = order;
+				if cancel.is_cancelled() {
+					continue;
+				}
+
 				let start = Instant::now();
-				let board = match mode {
-					GameMode::Sandbox => Board::new(size, 0, false),
-					GameMode::Learn => Board::new_learn(size, 0),
-					GameMode::Serious => Board::new_serious(size, 0),
+				let mut reported = (Phase::Filling, -1.0);
+				let mut report = |phase: Phase, done: f32| {
+					// a message for every percent is plenty
+					if phase != reported.0 || done - reported.1 >= 0.01 {
+						reported = (phase, done);
+						let _ = map_sender.send(Generated::Progress(id, phase, done));
+					}
+					!cancel.is_cancelled()
+				};
+				let board = match Kind::of(&mode) {
+					None => Some(Board::new(width, height, rules, 0, false)),
+					Some(kind) => pool.take(width, height, rules, kind).or_else(|| kind.generate(width, height, rules, &mut report)),
 				};
-				map_sender.send((board.map, id, start.elapsed().as_secs_f32())).unwrap();
+				// boards from the pool arrive at once and skip the generation animation
+				if let Some(board) = board {
+					map_sender.send(Generated::Board(board, id, start.elapsed().as_secs_f32())).unwrap();
+				}
 			}
 		});
 
@@ -114,7 +142,9 @@ impl Assets {
 			next_music_play: 0.0,
 			sender: order_sender,
 			receiver: map_receiver,
+			orders: Vec::new(),
 			next_board_id: 1,
+			campaign: Pack::load(CAMPAIGN).expect("the campaign levels are valid").puzzles,
 			assets_receiver,
 			background_render_target: render_target((screen_width()/crate::BACKGROUND_FACTOR) as u32, (screen_height()/crate::BACKGROUND_FACTOR) as u32),
 		}
@@ -156,12 +186,121 @@ impl Assets {
 		}
 	}
 
+	// the built in pack followed by every .json file in the packs directory, broken packs come with the reason
+	pub fn load_packs() -> Vec<Result<LoadedPack, String>> {
+		let mut packs = vec![Pack::load(STARTER_PACK)];
+
+		let mut paths: Vec<_> = fs::read_dir("packs").into_iter().flatten().flatten().map(|entry| entry.path()).filter(|path| path.extension().is_some_and(|e| e == "json")).collect();
+		paths.sort();
+		for path in paths {
+			let name = path.file_name().unwrap().to_string_lossy().to_string();
+			packs.push(fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|json| Pack::load(&json)).map_err(|e| format!("{name}: {e}")));
+		}
+
+		packs
+	}
+
+	// a printable sheet of the board with its answer key, as PDF and SVG in the exports directory. Returns the PDF path
+	pub fn export_sheet(board: &Board) -> Result<String, String> {
+		let puzzle = SheetPuzzle { label: format!("{}x{}", board.width, board.height), givens: board.clone(), solution: board.unique_solution() };
+		let pages = print::layout("Takuzu", &[puzzle], 1, true);
+
+		fs::create_dir_all("exports").map_err(|e| e.to_string())?;
+		let name = format!("exports/takuzu-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs());
+		fs::write(format!("{name}.svg"), print::svg(&pages[0])).map_err(|e| e.to_string())?;
+		fs::write(format!("{name}.pdf"), pdf::pdf(&pages)).map_err(|e| e.to_string())?;
+		Ok(format!("{name}.pdf"))
+	}
+
+	// renders the board offscreen with the player's colors and writes it as a PNG to the exports directory. Returns the path
+	pub fn save_image(&self, board: &Board, caption: Option<&str>) -> Result<String, String> {
+		if board.is_generating {
+			return Err("the board is still being generated".to_string());
+		}
+		let mut board = board.clone();
+		board.generation_duration = 0.0;
+		// halfway through the lock blink, so locked cells show their icon at full strength
+		board.show_locked = Some(get_time() as f32 - 0.5);
+
+		let pad = 0.05 + 0.3 * board.cell_size();
+		let caption_h = if caption.is_some() {0.12} else {0.0};
+		let r = board.rect();
+		let area = Rect { x: r.x - pad, y: r.y - pad, w: r.w + 2.0 * pad, h: r.h + 2.0 * pad + caption_h };
+		let width = 1024;
+		let height = (width as f32 * area.h / area.w).round() as u32;
+
+		let target = render_target(width, height);
+		set_camera(&Camera2D { render_target: Some(target), ..Camera2D::from_display_rect(area) });
+		clear_background(POPUP_EDGE_COL);
+		board.draw(self);
+		if let Some(caption) = caption {
+			draw_centered_text(vec2(area.center().x, r.bottom() + pad + caption_h * 0.4), caption, self.font, 0.06);
+		}
+		unsafe { get_internal_gl().flush() };
+		set_default_camera();
+
+		let mut image = target.texture.get_texture_data();
+		// blending leaves antialiased edges translucent
+		for pixel in image.bytes.chunks_mut(4) {
+			pixel[3] = 255;
+		}
+		target.delete();
+
+		fs::create_dir_all("exports").map_err(|e| e.to_string())?;
+		let path = format!("exports/takuzu-{}.png", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis());
+		image.export_png(&path);
+		Ok(path)
+	}
+
+	// won games go to the replays directory, named after the mode, the size and when they were played
+	pub fn save_replay(&self, replay: &Replay) -> Result<String, String> {
+		fs::create_dir_all("replays").map_err(|e| e.to_string())?;
+		let millis = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+		let path = format!("replays/{millis}-{}-{}x{}.replay", replay.mode, replay.puzzle.width, replay.puzzle.height);
+		fs::write(&path, replay.to_text()).map_err(|e| e.to_string())?;
+		Ok(path)
+	}
+
+	// the newest first, broken files come with the reason
+	pub fn load_replays() -> Vec<Result<Replay, String>> {
+		let mut paths: Vec<_> = fs::read_dir("replays").into_iter().flatten().flatten().map(|entry| entry.path()).filter(|path| path.extension().is_some_and(|e| e == "replay")).collect();
+		paths.sort();
+		paths.reverse();
+		paths.iter().map(|path| {
+			let name = path.file_name().unwrap().to_string_lossy().to_string();
+			fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|text| Replay::from_text(&text)).map_err(|e| format!("{name}: {e}"))
+		}).collect()
+	}
+
+	// the fastest verified serious game on the same puzzle, to race against
+	pub fn best_replay(board: &Board) -> Option<Replay> {
+		Self::load_replays().into_iter().flatten()
+			.filter(|replay| replay.mode == "serious" && replay.is_same_puzzle(board) && replay.verify().is_ok())
+			.min_by(|a, b| a.duration().total_cmp(&b.duration()))
+	}
+
+	pub fn order_board(&mut self, width: usize, height: usize, rules: Rules, mode: GameMode, id: usize) {
+		let cancel = CancelToken::default();
+		self.orders.push((id, cancel.clone()));
+		self.sender.send((width, height, rules, mode, id, cancel)).unwrap();
+	}
+
+	// stops generating the boards no one waits for anymore
+	pub fn cancel_orders(&mut self, wanted: &[usize]) {
+		self.orders.retain(|(id, cancel)| {
+			if !wanted.contains(id) {
+				cancel.cancel();
+			}
+			wanted.contains(id)
+		});
+	}
+
 	pub fn material(&self) -> &Material {
 		&self.materials[self.material]
 	}
 
 	pub fn change_material(&mut self) {
-		if let Option::None = self.secondary_material {
+		if self.secondary_material.is_none() {
 			self.secondary_material = Some((self.material, get_time() as f32));
 			self.material = rand::gen_range(0, self.materials.len());
 		}
@@ -206,37 +345,232 @@ impl Assets {
 	}
 }
 
+// what the generator thread sends back
+pub enum Generated {
+	Progress(usize, Phase, f32), // board id, phase, fraction of it done
+	Board(Board, usize, f32), // board, id, generation time
+}
+
+// set once the board ordered with it isn't needed anymore, checked by the generator as it goes
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+	pub fn cancel(&self) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+
+	pub fn is_cancelled(&self) -> bool {
+		self.0.load(Ordering::Relaxed)
+	}
+}
+
+// seconds a campaign level has to be solved in to earn its time star
+pub fn par_time(board: &Board) -> f32 {
+	(board.width * board.height) as f32
+}
+
+#[derive(Clone, Copy, Default, SerBin, DeBin)]
+pub struct LevelProgress {
+	pub best_time: f32,
+	pub time_star: bool, // solved within the par time
+	pub hint_star: bool, // solved without hints
+	pub error_star: bool, // solved without breaking a rule
+}
+
+impl LevelProgress {
+	pub fn stars(&self) -> [bool; 3] {
+		[self.time_star, self.hint_star, self.error_star]
+	}
+}
+
+#[derive(Clone, SerBin, DeBin)]
+pub struct Highscore {
+	pub width: usize,
+	pub height: usize,
+	pub rules: Rules,
+	pub time: f32,
+	pub strikes: Option<u32>, // set for strict games
+}
+
 #[derive(Clone, SerBin, DeBin)]
+pub struct Match {
+	pub width: usize,
+	pub height: usize,
+	pub rules: Rules,
+	pub winner: usize, // 0 for the player on the left
+	pub time: f32,
+}
+
+#[derive(Clone, SerBin, DeBin)]
+pub struct TimeAttackScore {
+	pub width: usize,
+	pub height: usize,
+	pub rules: Rules,
+	pub boards: usize, // the most solved in one session
+}
+
+// saves start with the header and the version of their layout, older layouts are still read.
+// 0 - before the header, one highscore per square size
+// 1 - separate width and height
+// 2 - rules for the highscores and the board settings
+// 3 - odd sizes in the rules
+// 4 - blocked cells in the rules
+// 5 - wrap-around in the rules
+// 6 - campaign progress
+// 7 - captions on saved images
+// 8 - strict games and their strikes
+// 9 - versus match history
+// 10 - last server address
+// 11 - time attack scores
+// 12 - zen mode count and auto hints
+const SAVE_HEADER: &[u8] = b"takuzu save";
+const SAVE_VERSION: u32 = 12;
+
+// the rules of a save, they gained a field in later versions
+fn read_rules(version: u32, d: &[u8], o: &mut usize) -> Result<Rules, DeBinErr> {
+	let mut rules = Rules { constraints: DeBin::de_bin(o, d)?, ..Rules::default() };
+	if version >= 3 {
+		rules.odd_sizes = DeBin::de_bin(o, d)?;
+	}
+	if version >= 4 {
+		rules.mask = DeBin::de_bin(o, d)?;
+	}
+	if version >= 5 {
+		rules.wrap = DeBin::de_bin(o, d)?;
+	}
+	Ok(rules)
+}
+
+#[derive(Clone, SerBin)]
 pub struct Persistance {
-	pub highscores: [Option<f32>; 10], // [map size/2 - 1] = time (2 - 20) -> (0 - 9)
+	pub highscores: Vec<Highscore>,
 	pub color0: [f32; 4],
 	pub color1: [f32; 4],
 	pub color2: [f32; 4],
-	pub game_size: usize,
+	pub game_width: usize,
+	pub game_height: usize,
+	pub game_rules: Rules,
 	pub music_volume: f32,
 	pub sfx_volume: f32,
+	pub campaign: Vec<LevelProgress>, // completed levels, the one after them is unlocked
+	pub image_caption: bool, // saved images show the mode and time below the board
+	pub strict: bool, // serious games punish cells placed against the solution
+	pub matches: Vec<Match>, // versus games in the order they were played
+	pub address: String, // the last server joined or hosted
+	pub time_attacks: Vec<TimeAttackScore>,
+	pub zen_solved: usize, // puzzles solved in zen mode, ever
+	pub auto_hints: bool, // zen mode hints after a while without a move
+}
+
+impl Default for Persistance {
+	fn default() -> Self {
+		Persistance {
+			highscores: Vec::new(),
+			color0: DARKGRAY.into(),
+			color1: Color { r: 1.0, g: 0.5, b: 0.0, a: 1.0 }.into(),
+			color2: Color { r: 0.0, g: 0.5, b: 1.0, a: 1.0 }.into(),
+			game_width: 4,
+			game_height: 4,
+			game_rules: Rules::default(),
+			music_volume: 1.0,
+			sfx_volume: 1.0,
+			campaign: Vec::new(),
+			image_caption: true,
+			strict: false,
+			matches: Vec::new(),
+			address: format!("127.0.0.1:{PORT}"),
+			time_attacks: Vec::new(),
+			zen_solved: 0,
+			auto_hints: true,
+		}
+	}
 }
 
 impl Persistance {
+	// strict and relaxed games keep separate highscores
+	pub fn highscore(&self, width: usize, height: usize, rules: Rules, strict: bool) -> Option<f32> {
+		self.highscores.iter().find(|s| s.width == width && s.height == height && s.rules == rules && s.strikes.is_some() == strict).map(|s| s.time)
+	}
+
 	// bool - is highscore, option - previous highscore
-	pub fn insert_highscore(&mut self, size: usize, time: f32) -> (bool, Option<f32>) {
-		if size > 20 {
-			return (false, None);
+	pub fn insert_highscore(&mut self, width: usize, height: usize, rules: Rules, time: f32, strikes: Option<u32>) -> (bool, Option<f32>) {
+		match self.highscores.iter_mut().find(|s| s.width == width && s.height == height && s.rules == rules && s.strikes.is_some() == strikes.is_some()) {
+			Some(entry) => {
+				let prev = entry.time;
+				if time < prev {
+					entry.time = time;
+					entry.strikes = strikes;
+					self.save();
+					return (true, Some(prev));
+				}
+				(false, None)
+			}
+			None => {
+				self.highscores.push(Highscore { width, height, rules, time, strikes });
+				self.highscores.sort_by_key(|s| (s.width * s.height, s.width, s.rules.label(), s.strikes.is_some()));
+				self.save();
+				(true, None)
+			}
 		}
+	}
 
-		if let Some(prev) = self.highscores[size/2 - 1] {
-			if time < prev {
-				self.highscores[size/2 - 1] = Some(time);
+	pub fn time_attack_best(&self, width: usize, height: usize, rules: Rules) -> Option<usize> {
+		self.time_attacks.iter().find(|s| s.width == width && s.height == height && s.rules == rules).map(|s| s.boards)
+	}
+
+	// bool - is the most boards yet, option - the previous most
+	pub fn insert_time_attack(&mut self, width: usize, height: usize, rules: Rules, boards: usize) -> (bool, Option<usize>) {
+		if boards == 0 {
+			return (false, None);
+		}
+		match self.time_attacks.iter_mut().find(|s| s.width == width && s.height == height && s.rules == rules) {
+			Some(entry) if boards > entry.boards => {
+				let prev = entry.boards;
+				entry.boards = boards;
 				self.save();
-				return (true, Some(prev));
+				(true, Some(prev))
+			}
+			Some(_) => (false, None),
+			None => {
+				self.time_attacks.push(TimeAttackScore { width, height, rules, boards });
+				self.time_attacks.sort_by_key(|s| (s.width * s.height, s.width, s.rules.label()));
+				self.save();
+				(true, None)
 			}
-			return (false, None);
 		}
-		else {
-			self.highscores[size/2 - 1] = Some(time);
-			self.save();
-			return (true, None);
+	}
+
+	pub fn record_match(&mut self, width: usize, height: usize, rules: Rules, winner: usize, time: f32) {
+		self.matches.push(Match { width, height, rules, winner, time });
+		self.save();
+	}
+
+	// games won by the left and the right player
+	pub fn wins(&self) -> [usize; 2] {
+		let left = self.matches.iter().filter(|m| m.winner == 0).count();
+		[left, self.matches.len() - left]
+	}
+
+	pub fn is_unlocked(&self, level: usize) -> bool {
+		level <= self.campaign.len()
+	}
+
+	// keeps the best time and every star ever earned on the level, returns the progress before this run
+	pub fn complete_level(&mut self, level: usize, time: f32, par_time: f32, hints: u32, errors: u32) -> Option<LevelProgress> {
+		let run = LevelProgress { best_time: time, time_star: time <= par_time, hint_star: hints == 0, error_star: errors == 0 };
+		let previous = self.campaign.get(level).copied();
+		match self.campaign.get_mut(level) {
+			Some(progress) => {
+				progress.best_time = progress.best_time.min(time);
+				progress.time_star |= run.time_star;
+				progress.hint_star |= run.hint_star;
+				progress.error_star |= run.error_star;
+			}
+			None => self.campaign.push(run),
 		}
+		self.save();
+		previous
 	}
 
 	pub fn load() -> Self {
@@ -245,35 +579,98 @@ impl Persistance {
 			Ok(f) => {
 				let mut vec = Vec::new();
 				f.read_to_end(&mut vec).unwrap();
-				match Self::deserialize_bin(&vec) {
-					Ok(data) => data,
-					Err(_) => Persistance {
-						highscores: [None; 10],
-						color0: DARKGRAY.into(),
-						color1: Color { r: 1.0, g: 0.5, b: 0.0, a: 1.0 }.into(),
-						color2: Color { r: 0.0, g: 0.5, b: 1.0, a: 1.0 }.into(),
-						game_size: 4,
-						music_volume: 1.0,
-						sfx_volume: 1.0,
-					}
+				// saves from before the header are version 0
+				let mut o = 0;
+				let version = if vec.starts_with(SAVE_HEADER) {
+					o = SAVE_HEADER.len();
+					u32::de_bin(&mut o, &vec).unwrap_or(u32::MAX)
 				}
+				else {
+					0
+				};
+				if version > SAVE_VERSION {
+					eprintln!("the save is from a newer version of the game, starting with the default settings");
+					return Persistance::default();
+				}
+				Self::read(version, &vec, &mut o).unwrap_or_else(|e| {
+					eprintln!("can't read the save, starting with the default settings: {e:?}");
+					Persistance::default()
+				})
 			},
-			Err(_) => {
-				Persistance {
-					highscores: [None; 10],
-					color0: DARKGRAY.into(),
-					color1: Color { r: 1.0, g: 0.5, b: 0.0, a: 1.0 }.into(),
-					color2: Color { r: 0.0, g: 0.5, b: 1.0, a: 1.0 }.into(),
-					game_size: 4,
-					music_volume: 1.0,
-					sfx_volume: 1.0,
+			Err(_) => Persistance::default(),
+		}
+	}
+
+	// a save with the layout of its version, the fields added after it keep their defaults
+	fn read(version: u32, d: &[u8], o: &mut usize) -> Result<Self, DeBinErr> {
+		let highscores = match version {
+			0 => {
+				// one time for each square size from 2x2 to 20x20
+				let times: [Option<f32>; 10] = DeBin::de_bin(o, d)?;
+				times.iter().enumerate().filter_map(|(i, time)| {
+					time.map(|time| Highscore { width: 2 * i + 2, height: 2 * i + 2, rules: Rules::default(), time, strikes: None })
+				}).collect()
+			}
+			1 => {
+				let times: Vec<(usize, usize, f32)> = DeBin::de_bin(o, d)?;
+				times.into_iter().map(|(width, height, time)| Highscore { width, height, rules: Rules::default(), time, strikes: None }).collect()
+			}
+			_ => {
+				let len: usize = DeBin::de_bin(o, d)?;
+				let mut highscores = Vec::new();
+				for _ in 0..len {
+					highscores.push(Highscore { width: DeBin::de_bin(o, d)?, height: DeBin::de_bin(o, d)?, rules: read_rules(version, d, o)?, time: DeBin::de_bin(o, d)?, strikes: if version >= 8 {DeBin::de_bin(o, d)?} else {None} });
 				}
+				highscores
 			}
+		};
+		let mut save = Persistance { highscores, ..Persistance::default() };
+		save.color0 = DeBin::de_bin(o, d)?;
+		save.color1 = DeBin::de_bin(o, d)?;
+		save.color2 = DeBin::de_bin(o, d)?;
+		if version == 0 {
+			let size: usize = DeBin::de_bin(o, d)?;
+			(save.game_width, save.game_height) = (size, size);
+		}
+		else {
+			save.game_width = DeBin::de_bin(o, d)?;
+			save.game_height = DeBin::de_bin(o, d)?;
+		}
+		if version >= 2 {
+			save.game_rules = read_rules(version, d, o)?;
+		}
+		save.music_volume = DeBin::de_bin(o, d)?;
+		save.sfx_volume = DeBin::de_bin(o, d)?;
+		if version >= 6 {
+			save.campaign = DeBin::de_bin(o, d)?;
+		}
+		if version >= 7 {
+			save.image_caption = DeBin::de_bin(o, d)?;
+		}
+		if version >= 8 {
+			save.strict = DeBin::de_bin(o, d)?;
+		}
+		if version >= 9 {
+			save.matches = DeBin::de_bin(o, d)?;
+		}
+		if version >= 10 {
+			save.address = DeBin::de_bin(o, d)?;
+		}
+		if version >= 11 {
+			save.time_attacks = DeBin::de_bin(o, d)?;
+		}
+		if version >= 12 {
+			save.zen_solved = DeBin::de_bin(o, d)?;
+			save.auto_hints = DeBin::de_bin(o, d)?;
 		}
+		Ok(save)
 	}
 
 	pub fn save(&self) {
+		let mut data = SAVE_HEADER.to_vec();
+		SAVE_VERSION.ser_bin(&mut data);
+		self.ser_bin(&mut data);
 		let mut file = File::create("save").unwrap();
-		file.write_all(self.serialize_bin().as_slice()).unwrap();
+		file.write_all(&data).unwrap();
 	}
 }
\ No newline at end of file
@@ -5,9 +5,11 @@ use macroquad::{prelude::Color, time::get_time};
 
 pub mod board;
 pub mod cell_state;
+pub use takuzu_core::{constraint, rules};
 pub mod state;
 pub mod ui;
 pub mod assets;
+pub mod pool;
 
 const BACKGROUND_FACTOR: f32 = 5.0;
 
@@ -15,6 +17,8 @@ const FONT: &[u8] = include_bytes!("../assets/Jellee-Bold.ttf");
 const GRADIENT: &[u8] = include_bytes!("../assets/gradient.png");
 const LOCK: &[u8] = include_bytes!("../assets/lock.png");
 const BANNER: &[u8] = include_bytes!("../assets/banner.png");
+const STARTER_PACK: &str = include_str!("../assets/packs/starter.json");
+const CAMPAIGN: &str = include_str!("../assets/campaign.json");
 
 const MUSIC: [&[u8]; 4] = [
 	include_bytes!("../assets/music/abstract-world-127012.ogg"),
@@ -48,6 +52,13 @@ const SEC_BUTTON_COL: Color = Color {r: 0.48, g: 0.54, b: 0.68, a: 0.5};
 
 const SLIDER_COL: Color = Color {r: 0.48, g: 0.54, b: 0.68, a: 0.7};
 
+const TRIPLET_COL: Color = Color {r: 1.0, g: 0.0, b: 0.0, a: 1.0};
+const BALANCE_COL: Color = Color {r: 1.0, g: 0.8, b: 0.0, a: 1.0};
+const DUPLICATE_COL: Color = Color {r: 0.8, g: 0.0, b: 1.0, a: 1.0};
+const CONSTRAINT_COL: Color = Color {r: 0.0, g: 1.0, b: 0.8, a: 1.0};
+
+const BLOCKED_COL: Color = Color {r: 0.08, g: 0.08, b: 0.08, a: 0.9};
+
 const POPUP_EDGE_COL: Color = Color {r: 0.1, g: 0.1, b: 0.1, a: 1.0};
 const POPUP_COL: Color = Color {r: 0.3, g: 0.3, b: 0.3, a: 1.0};
 
@@ -64,8 +75,8 @@ pub fn col_lerp(a: Color, b: Color, t: f32) -> Color {
 	}
 }
 
-pub fn generation_animation_cell_col(x: f32, y: f32, size: f32, assets: &Assets) -> Color {
-	let angle = (y - size / 2.0).atan2(x - size / 2.0);
+pub fn generation_animation_cell_col(x: f32, y: f32, width: f32, height: f32, assets: &Assets) -> Color {
+	let angle = (y - height / 2.0).atan2(x - width / 2.0);
 	let col = (angle / 2.0 / PI + get_time() as f32 * 0.3) % 1.0;
 
 	let mut col = col_lerp(assets.persistance.color2.into(), assets.persistance.color1.into(), col);
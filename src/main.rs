@@ -1,6 +1,6 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 use macroquad::{self, prelude::*, miniquad::conf::Icon};
-use takuzu::{state::State, assets::Assets};
+use takuzu::{state::State, assets::{Assets, Generated}};
 
 /*
 	TODO:
@@ -12,8 +12,6 @@ use takuzu::{state::State, assets::Assets};
 		sandbox lock/unlock
 		zoom :weary:
 
-		sandbox algorithms multithreading
-
 		adjust allocated spaces to really used ones
 */
 
@@ -41,10 +39,21 @@ async fn main() {
 		
 		if let Some(s) = state.update(&mut assets, true) {
 			state = s;
+			assets.cancel_orders(&state.ordered_boards());
+		}
+
+		if is_key_pressed(KeyCode::F12) {
+			state.save_image(&assets);
 		}
 
-		if let Ok((map, id, time)) = assets.receiver.try_recv() {
-			state.capture_generated_map(map.len(), map, id, time);
+		while let Ok(generated) = assets.receiver.try_recv() {
+			match generated {
+				Generated::Progress(id, phase, done) => state.capture_progress(id, phase, done),
+				Generated::Board(board, id, time) => {
+					assets.orders.retain(|(order, _)| *order != id);
+					state.capture_generated_board(board, id, time);
+				}
+			}
 		}
 
         next_frame().await
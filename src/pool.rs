@@ -0,0 +1,153 @@
+use std::fs;
+
+use nanoserde::{DeBin, SerBin};
+use takuzu_core::format;
+use crate::{board::{Board, Phase}, rules::Rules, state::GameMode};
+
+const FILE: &str = "pool";
+const SIZES: usize = 4; // the latest sizes and kinds kept ready
+const READY: usize = 2; // boards kept ready for each
+
+// the ways boards are generated, several modes share one
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+	Learn,
+	Serious,
+}
+
+impl Kind {
+	// sandbox boards start empty and aren't worth keeping
+	pub fn of(mode: &GameMode) -> Option<Self> {
+		match mode {
+			GameMode::Sandbox => None,
+			GameMode::Learn | GameMode::Zen => Some(Kind::Learn),
+			GameMode::Serious | GameMode::Versus | GameMode::TimeAttack => Some(Kind::Serious),
+		}
+	}
+
+	pub fn as_str(&self) -> &str {
+		match self {
+			Kind::Learn => "learn",
+			Kind::Serious => "serious",
+		}
+	}
+
+	fn parse(s: &str) -> Option<Self> {
+		match s {
+			"learn" => Some(Kind::Learn),
+			"serious" => Some(Kind::Serious),
+			_ => None,
+		}
+	}
+
+	// None if report stopped it
+	pub fn generate(&self, width: usize, height: usize, rules: Rules, report: &mut dyn FnMut(Phase, f32) -> bool) -> Option<Board> {
+		Board::new_puzzle(width, height, rules, 0, *self == Kind::Learn, true, report)
+	}
+}
+
+// the boards kept ready for one size, rules and kind
+struct Entry {
+	width: usize,
+	height: usize,
+	rules: Rules,
+	kind: Kind,
+	boards: Vec<Board>,
+}
+
+// an entry as it is saved. The text format only knows the size and the markers, so the rules are kept beside the boards
+#[derive(SerBin, DeBin)]
+struct Saved {
+	kind: String,
+	width: usize,
+	height: usize,
+	rules: Rules,
+	boards: String, // in the text format
+}
+
+// puzzles generated ahead of time, so that the next game of a recently played size starts right away
+pub struct Pool {
+	entries: Vec<Entry>, // the latest played first
+}
+
+impl Pool {
+	// a pool saved in an older layout is dropped, it only saves generating a few boards
+	pub fn load() -> Self {
+		Self::from_bytes(&fs::read(FILE).unwrap_or_default())
+	}
+
+	pub fn from_bytes(data: &[u8]) -> Self {
+		let saved: Vec<Saved> = DeBin::deserialize_bin(data).unwrap_or_default();
+		let entries = saved.into_iter().filter_map(|entry| {
+			let kind = Kind::parse(&entry.kind)?;
+			let boards = format::from_text(&entry.boards).unwrap_or_default().into_iter()
+				.filter(|b| (b.width, b.height) == (entry.width, entry.height))
+				.map(|b| Board { rules: entry.rules, ..b })
+				.collect();
+			Some(Entry { width: entry.width, height: entry.height, rules: entry.rules, kind, boards })
+		}).collect();
+		Pool { entries }
+	}
+
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let saved: Vec<Saved> = self.entries.iter().map(|entry| Saved {
+			kind: entry.kind.as_str().to_string(),
+			width: entry.width,
+			height: entry.height,
+			rules: entry.rules,
+			boards: entry.boards.iter().map(format::to_text).collect::<Vec<_>>().join("\n"),
+		}).collect();
+		saved.serialize_bin()
+	}
+
+	pub fn save(&self) {
+		if let Err(e) = fs::write(FILE, self.to_bytes()) {
+			eprintln!("can't save the board pool: {e}");
+		}
+	}
+
+	// a ready board if there is one. Either way the size becomes the latest played, so it's filled first
+	pub fn take(&mut self, width: usize, height: usize, rules: Rules, kind: Kind) -> Option<Board> {
+		let i = self.entries.iter().position(|e| (e.width, e.height, e.rules, e.kind) == (width, height, rules, kind));
+		let mut entry = match i {
+			Some(i) => self.entries.remove(i),
+			None => Entry { width, height, rules, kind, boards: Vec::new() },
+		};
+		let board = entry.boards.pop();
+		self.entries.insert(0, entry);
+		self.entries.truncate(SIZES);
+		self.save();
+		board
+	}
+
+	// generates a board for the latest size that is missing some, false once all of them are ready
+	pub fn fill_one(&mut self, report: &mut dyn FnMut(Phase, f32) -> bool) -> bool {
+		let Some(entry) = self.entries.iter_mut().find(|e| e.boards.len() < READY) else { return false };
+		if let Some(board) = entry.kind.generate(entry.width, entry.height, entry.rules, report) {
+			entry.boards.push(board);
+			self.save();
+		}
+		true
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{cell_state::CellState, rules::Mask};
+
+	#[test]
+	fn entries_keep_their_rules() {
+		let rules = Rules { mask: Mask::Random, ..Rules::default() };
+		let board = Kind::Serious.generate(6, 6, rules, &mut |_, _| true).unwrap();
+		let pool = Pool { entries: vec![Entry { width: 6, height: 6, rules, kind: Kind::Serious, boards: vec![board.clone()] }] };
+
+		let loaded = Pool::from_bytes(&pool.to_bytes());
+		let entry = &loaded.entries[0];
+		assert_eq!(entry.rules, rules);
+		assert_eq!(entry.boards.len(), 1);
+		assert_eq!(entry.boards[0].rules, rules);
+		let blocked = |b: &Board| b.map.iter().flatten().filter(|c| **c == CellState::Blocked).count();
+		assert_eq!(blocked(&entry.boards[0]), blocked(&board));
+	}
+}
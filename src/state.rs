@@ -1,13 +1,17 @@
-use std::f32::consts::PI;
+use std::{f32::consts::PI, env, rc::Rc, collections::VecDeque};
 
-use crate::{board::Board, ui::{rect_circumscribed_on_rect, button, draw_centered_text_stable, draw_round_rect, draw_centered_text, draw_centered_text_color, slider}, assets::Assets, PRI_BUTTON_COL, SEC_BUTTON_COL, SLIDER_COL, POPUP_COL, POPUP_EDGE_COL, FORWARD, BACKWARD, TICK, cell_state::CellState};
+use crate::{board::{Board, BoardUi, Violation, Rule, Phase}, cell_state::CellState, rules::{Rules, Mask}, constraint::Constraint, ui::{rect_circumscribed_on_rect, button, draw_centered_text_stable, draw_round_rect, draw_centered_text, draw_centered_text_color, slider}, assets::{Assets, par_time}, PRI_BUTTON_COL, SEC_BUTTON_COL, SLIDER_COL, POPUP_COL, POPUP_EDGE_COL, FORWARD, BACKWARD, TICK, ERROR, POP, LOCKED};
 use macroquad::prelude::*;
+use takuzu_core::{pack::LoadedPack, replay::{Replay, Action}, net::{Client, Server, Message, Mode, COUNTDOWN, PORT}};
 
 #[derive(Clone)]
 pub enum GameMode {
 	Sandbox,
 	Learn,
 	Serious,
+	Versus,
+	TimeAttack,
+	Zen,
 }
 
 impl GameMode {
@@ -16,6 +20,9 @@ impl GameMode {
 			Self::Sandbox => "Sandbox",
 			Self::Learn => "Learn",
 			Self::Serious => "Serious",
+			Self::Versus => "Versus",
+			Self::TimeAttack => "Time attack",
+			Self::Zen => "Zen",
 		}
 	}
 
@@ -24,23 +31,260 @@ impl GameMode {
 			Self::Sandbox => vec!["Here you can play", "around with different", "algorithms."],
 			Self::Learn =>   vec!["Here you can play", "Takuzu with hints", "and error highlighting"],
 			Self::Serious => vec!["Here you can play", "Takuzu without any", "hints or highlighting.", "Your best times will be", "saved as highscores."],
+			Self::Versus =>  vec!["Two players race on", "the same puzzle, one", "with WASD, Q and E,", "one with the arrows", "and numpad 0 and 1."],
+			Self::Zen =>     vec!["Endless puzzles", "without a clock or", "scores, one after", "another. Hints can", "come by themselves."],
+			Self::TimeAttack => vec!["Solve as many boards", "as you can in", "five minutes. The most", "boards for each size", "are saved."],
 		}
 	}
 }
 
+const STRIKE_PENALTY: f32 = 10.0;
+const TIME_ATTACK: f32 = 300.0;
+const PREFETCHED: usize = 3; // boards generated ahead in time attack
+const ZEN_HINT_DELAY: f32 = 20.0; // seconds without a move before a hint shows up
+const ZEN_ADVANCE: f32 = 2.0; // seconds a solved board stays before the next one
+const MAX_STRIKES: u32 = 3;
+const STRIKE_SETTLE: f32 = 1.0; // seconds a wrong cell has to stay to count, clicking through a color on the way to the other one doesn't
+// macroquad doesn't report focus changes, so a window that is only in the background keeps the game running.
+// The frame loop stalls while the window is minimized or hidden though, a frame this long means it was
+const HIDDEN_STALL: f32 = 2.0;
+
+// strict serious games: every cell placed against the solution costs time and a strike
+#[derive(Clone, Default)]
+pub struct Strict {
+	pub solution: Option<Box<Board>>, // found once the board is generated
+	pub strikes: u32,
+	pub pending: Vec<(usize, usize, f32)>, // wrong cells and when they were placed, not counted yet
+}
+
+impl Strict {
+	pub fn new(board: &Board) -> Self {
+		Strict { solution: if board.is_generating {None} else {board.unique_solution().map(Box::new)}, ..Default::default() }
+	}
+}
+
+#[derive(Clone)]
+pub struct SeriousGame {
+	pub board: Board,
+	pub start_time: f32,
+	pub finished_time: Option<f32>,
+	pub sounds: usize, // completed tick sound plays
+	pub paused: f32, // time spent paused
+	pub strict: Option<Strict>,
+	pub replay: Replay, // recording
+	pub ghost: Option<Box<Replay>>, // best earlier run on this puzzle
+}
+
+impl SeriousGame {
+	pub fn new(board: Board, strict: Option<Strict>, replay: Replay, ghost: Option<Box<Replay>>) -> Self {
+		SeriousGame { board, start_time: get_time() as f32 + 1.5, finished_time: None, sounds: 0, paused: 0.0, strict, replay, ghost }
+	}
+}
+
+#[derive(Clone)]
+pub struct TimeAttackGame {
+	pub board: Board,
+	pub start_time: f32,
+	pub sounds: usize, // completed tick sound plays
+	pub solved: usize, // boards solved
+	pub queue: VecDeque<Board>, // boards generated ahead
+	pub pending: Vec<usize>, // ids of the ordered boards
+}
+
+#[derive(Clone)]
+pub struct ZenGame {
+	pub board: Board,
+	pub next: Option<Board>, // board generated ahead
+	pub next_id: usize, // the id it was ordered with
+	pub solved: usize, // puzzles solved this session
+	pub last_move: f32,
+	pub solved_at: Option<f32>, // when the board was solved
+}
+
+// keys of a player in versus games
+pub struct Controls {
+	pub up: KeyCode,
+	pub down: KeyCode,
+	pub left: KeyCode,
+	pub right: KeyCode,
+	pub zero: KeyCode,
+	pub one: KeyCode,
+	pub help: &'static str,
+}
+
+// who filled a cell in a co-op game, by their id on the server
+const OWNER_COLS: [Color; 4] = [ORANGE, SKYBLUE, PINK, LIME];
+
+const CONTROLS: [Controls; 2] = [
+	Controls { up: KeyCode::W, down: KeyCode::S, left: KeyCode::A, right: KeyCode::D, zero: KeyCode::Q, one: KeyCode::E, help: "WASD to move, Q and E to fill" },
+	Controls { up: KeyCode::Up, down: KeyCode::Down, left: KeyCode::Left, right: KeyCode::Right, zero: KeyCode::Kp0, one: KeyCode::Kp1, help: "arrows to move, numpad 0 and 1 to fill" },
+];
+
+// one side of a versus game, with a board of its own
+#[derive(Clone)]
+pub struct Player {
+	pub board: Board,
+	pub cursor: (usize, usize),
+}
+
+impl Player {
+	pub fn new(board: &Board) -> Self {
+		Player { board: board.clone(), cursor: (0, 0) }
+	}
+
+	// the cursor wraps around the edges, filling a cell with what it already holds empties it
+	fn handle_keys(&mut self, controls: &Controls, assets: &Assets) {
+		let (width, height) = (self.board.width, self.board.height);
+		let (x, y) = &mut self.cursor;
+		if is_key_pressed(controls.left) {*x = (*x + width - 1) % width;}
+		if is_key_pressed(controls.right) {*x = (*x + 1) % width;}
+		if is_key_pressed(controls.up) {*y = (*y + height - 1) % height;}
+		if is_key_pressed(controls.down) {*y = (*y + 1) % height;}
+
+		let state = if is_key_pressed(controls.zero) {CellState::False(false)} else if is_key_pressed(controls.one) {CellState::True(false)} else {return};
+		let (x, y) = self.cursor;
+		let cell = &mut self.board.map[y][x];
+		if *cell == CellState::Blocked {
+			return;
+		}
+		if cell.is_locked() {
+			self.board.show_locked = Some(get_time() as f32);
+			assets.play_sound(LOCKED);
+			return;
+		}
+		*cell = if *cell == state {CellState::None} else {state};
+		assets.play_sound(POP);
+		self.board.verify_board_now();
+	}
+
+	fn draw_cursor(&self) {
+		let w = self.board.cell_size();
+		let origin = self.board.rect().point();
+		let (x, y) = self.cursor;
+		draw_rectangle_lines(origin.x + x as f32 * w, origin.y + y as f32 * w, w, w, 0.08 * w, WHITE);
+	}
+}
+
+// a race or a co-op game over the network, whoever hosts runs the server as well
+#[derive(Clone)]
+pub struct Online {
+	pub client: Rc<Client>,
+	pub server: Option<Rc<Server>>, // stops when the host leaves
+	pub mode: Mode,
+	pub board: Option<Board>, // until the server hands out a puzzle
+	pub index: usize, // the player's number on the server
+	pub opponent: Option<String>,
+	pub players: Vec<(usize, String)>, // everyone else in a co-op game
+	pub owners: Vec<Vec<Option<usize>>>, // who filled each cell in a co-op game
+	pub versions: Vec<Vec<u32>>, // moves on each cell the server knows of
+	pub opponent_progress: usize,
+	pub start_time: f32,
+	pub sounds: usize, // completed tick sound plays
+	pub result: Option<(usize, f32)>, // winner and time
+	pub rematch: (bool, bool), // asked for by the player, by the opponent
+	pub status: String, // what went wrong last
+}
+
+impl Online {
+	pub fn new(client: Client, server: Option<Server>) -> Self {
+		Online { client: Rc::new(client), server: server.map(Rc::new), mode: Mode::Race, board: None, index: 0, opponent: None, players: Vec::new(), owners: Vec::new(), versions: Vec::new(), opponent_progress: 0, start_time: 0.0, sounds: 0, result: None, rematch: (false, false), status: String::new() }
+	}
+
+	fn handle(&mut self, message: Message, assets: &mut Assets) {
+		match message {
+			Message::Mode(mode) => self.mode = mode,
+			Message::Welcome(index) => self.index = index,
+			Message::Opponent(name) => self.opponent = Some(name),
+			Message::Player(id, name) => self.players.push((id, name)),
+			Message::Gone(id) => {
+				if let Some(i) = self.players.iter().position(|(other, _)| *other == id) {
+					self.status = format!("{} left", self.players.remove(i).1);
+				}
+			}
+			Message::Puzzle(mut board) => {
+				board.id = assets.next_board_id;
+				assets.next_board_id += 1;
+				self.owners = vec![vec![None; board.width]; board.height];
+				self.versions = vec![vec![0; board.width]; board.height];
+				self.board = Some(board);
+				self.start_time = get_time() as f32 + COUNTDOWN;
+				self.sounds = 0;
+				self.opponent_progress = 0;
+				self.result = None;
+				self.rematch = (false, false);
+				self.status.clear();
+			}
+			Message::Progress(cells) => self.opponent_progress = cells,
+			// the server's word on a cell, which undoes any move of ours it turned down
+			Message::Cell(x, y, cell, owner, version) => {
+				let Some(board) = &mut self.board else { return };
+				if x < board.width && y < board.height {
+					board.map[y][x] = cell;
+					self.owners[y][x] = owner;
+					self.versions[y][x] = version;
+					board.verify_board_now();
+				}
+			}
+			Message::Rejected(reason) => {
+				self.status = reason;
+				assets.play_sound(ERROR);
+			}
+			Message::Result(winner, time) => {
+				self.result = Some((winner, time));
+				assets.play_sound(if winner == self.index || self.mode == Mode::Coop {FORWARD} else {BACKWARD});
+			}
+			Message::Rematch => self.rematch.1 = true,
+			Message::Left => {
+				self.board = None;
+				self.result = None;
+				// a full server says so before it closes the connection
+				if let Some(name) = self.opponent.take() {
+					self.status = format!("{name} left");
+				}
+				else if self.server.is_none() && self.status.is_empty() {
+					self.status = "the connection was closed".to_string();
+				}
+			}
+			Message::Full => self.status = match self.mode {
+				Mode::Race => "two players are racing on this server already".to_string(),
+				Mode::Coop => "this co-op game is full".to_string(),
+			},
+			Message::Hello(_) | Message::Finish(_) | Message::Set(..) => {}
+		}
+	}
+}
+
+fn player_name() -> String {
+	env::var("USER").or_else(|_| env::var("USERNAME")).unwrap_or("player".to_string())
+}
+
 #[derive(Clone)]
 pub enum State {
 	MainMenu,
 	Sandbox(Board),
-	Learn(Board),
-	Serious(Board, f32, Option<f32>, usize), // start time, finished time, completed tick sound plays
+	Learn(Board, Replay),
+	Serious(SeriousGame),
+	Paused(Box<State>, f32), // the paused game, when the pause started
 	EndScreen(Box<State>, Option<(f32, Option<f32>)>), // is highscore - new time, previous time (if any)
 	ExitConfirmation(Box<State>),
-	Highscores,
+	Highscores(f32), // scroll
 	Settings(Board),
-	DifficultyChoice(Board, GameMode, usize), 
+	DifficultyChoice(Board, GameMode, usize, usize, Rules), // width, height, rules
 	Attribution,
-	ModeInfo(GameMode)
+	ModeInfo(GameMode),
+	Packs(Vec<Result<LoadedPack, String>>, f32), // installed packs or why they failed to load, scroll
+	PackPuzzles(LoadedPack, f32), // scroll
+	Campaign(f32), // scroll
+	CampaignLevel(Board, usize, f32, Option<f32>, u32, u32), // level, start time, finished time, hints taken, rule breaks
+	Replays(Vec<Result<Replay, String>>, f32), // saved replays or why they failed to load, scroll
+	Replay(Replay, f32, f32, bool, Result<(), String>), // position, speed, playing, whether the moves lead to the recorded win
+	Versus(Vec<Player>, f32, usize, Option<(usize, f32)>), // start time, completed tick sound plays, winner and time
+	Matches(f32), // scroll
+	TimeAttack(TimeAttackGame),
+	TimeAttackScores(f32), // scroll
+	Zen(ZenGame),
+	Lobby(String, String, Mode), // address, what went wrong, what to host
+	Online(Box<Online>),
 }
 
 impl State {
@@ -65,57 +309,99 @@ impl State {
 					pivot: None 
 				});
 				
-				if button(&Rect{x: 0.3, y: 0.28, w: 0.4, h: 0.1}, PRI_BUTTON_COL, "SANDBOX", &cam, font, 0.06) && handle_mouse {
-					let mut board = Board::new(assets.persistance.game_size, 0, false);
-					board.generate_fraction(0.6);
-					ret = Some(Self::DifficultyChoice(board, GameMode::Sandbox, assets.persistance.game_size));
+				if button(&Rect{x: 0.3, y: 0.25, w: 0.4, h: 0.08}, PRI_BUTTON_COL, "SANDBOX", &cam, font, 0.06) && handle_mouse {
+					let board = Board::new_preview(assets.persistance.game_width, assets.persistance.game_height, assets.persistance.game_rules);
+					ret = Some(Self::DifficultyChoice(board, GameMode::Sandbox, assets.persistance.game_width, assets.persistance.game_height, assets.persistance.game_rules));
+					assets.play_sound(FORWARD);
+				}
+				if button(&Rect{x: 0.3, y: 0.34, w: 0.195, h: 0.08}, PRI_BUTTON_COL, "LEARN", &cam, font, 0.05) && handle_mouse {
+					let board = Board::new_preview(assets.persistance.game_width, assets.persistance.game_height, assets.persistance.game_rules);
+					ret = Some(Self::DifficultyChoice(board, GameMode::Learn, assets.persistance.game_width, assets.persistance.game_height, assets.persistance.game_rules));
+					assets.play_sound(FORWARD);
+				}
+				if button(&Rect{x: 0.505, y: 0.34, w: 0.195, h: 0.08}, PRI_BUTTON_COL, "ZEN", &cam, font, 0.05) && handle_mouse {
+					let board = Board::new_preview(assets.persistance.game_width, assets.persistance.game_height, assets.persistance.game_rules);
+					ret = Some(Self::DifficultyChoice(board, GameMode::Zen, assets.persistance.game_width, assets.persistance.game_height, assets.persistance.game_rules));
+					assets.play_sound(FORWARD);
+				}
+				if button(&Rect{x: 0.3, y: 0.43, w: 0.195, h: 0.08}, PRI_BUTTON_COL, "SERIOUS", &cam, font, 0.05) && handle_mouse {
+					let board = Board::new_preview(assets.persistance.game_width, assets.persistance.game_height, assets.persistance.game_rules);
+					ret = Some(Self::DifficultyChoice(board, GameMode::Serious, assets.persistance.game_width, assets.persistance.game_height, assets.persistance.game_rules));
 					assets.play_sound(FORWARD);
 				}
-				if button(&Rect{x: 0.3, y: 0.39, w: 0.4, h: 0.1}, PRI_BUTTON_COL, "LEARN", &cam, font, 0.06) && handle_mouse {
-					let mut board = Board::new(assets.persistance.game_size, 0, false);
-					board.generate_fraction(0.6);
-					ret = Some(Self::DifficultyChoice(board, GameMode::Learn, assets.persistance.game_size));
+				if button(&Rect{x: 0.505, y: 0.43, w: 0.195, h: 0.08}, PRI_BUTTON_COL, "TIME ATTACK", &cam, font, 0.03) && handle_mouse {
+					let board = Board::new_preview(assets.persistance.game_width, assets.persistance.game_height, assets.persistance.game_rules);
+					ret = Some(Self::DifficultyChoice(board, GameMode::TimeAttack, assets.persistance.game_width, assets.persistance.game_height, assets.persistance.game_rules));
 					assets.play_sound(FORWARD);
 				}
-				if button(&Rect{x: 0.3, y: 0.5, w: 0.4, h: 0.1}, PRI_BUTTON_COL, "SERIOUS", &cam, font, 0.06) && handle_mouse {
-					let mut board = Board::new(assets.persistance.game_size, 0, false);
-					board.generate_fraction(0.6);
-					ret = Some(Self::DifficultyChoice(board, GameMode::Serious, assets.persistance.game_size));
+				if button(&Rect{x: 0.505, y: 0.52, w: 0.195, h: 0.08}, PRI_BUTTON_COL, "ONLINE", &cam, font, 0.05) && handle_mouse {
+					// keys typed on other screens would end up in the address
+					while get_char_pressed().is_some() {}
+					ret = Some(Self::Lobby(assets.persistance.address.clone(), String::new(), Mode::Race));
+					assets.play_sound(FORWARD);
+				}
+				if button(&Rect{x: 0.3, y: 0.52, w: 0.195, h: 0.08}, PRI_BUTTON_COL, "VERSUS", &cam, font, 0.05) && handle_mouse {
+					let board = Board::new_preview(assets.persistance.game_width, assets.persistance.game_height, assets.persistance.game_rules);
+					ret = Some(Self::DifficultyChoice(board, GameMode::Versus, assets.persistance.game_width, assets.persistance.game_height, assets.persistance.game_rules));
 					assets.play_sound(FORWARD);
 				}
 				
-				if button(&Rect{x: 0.2, y: 0.29, w: 0.08, h: 0.08}, SEC_BUTTON_COL, "?", &cam, font, 0.05) && handle_mouse {
+				if button(&Rect{x: 0.21, y: 0.255, w: 0.07, h: 0.07}, SEC_BUTTON_COL, "?", &cam, font, 0.05) && handle_mouse {
 					ret = Some(Self::ModeInfo(GameMode::Sandbox));
 					assets.play_sound(FORWARD);
 				}
-				if button(&Rect{x: 0.2, y: 0.40, w: 0.08, h: 0.08}, SEC_BUTTON_COL, "?", &cam, font, 0.05) && handle_mouse {
+				if button(&Rect{x: 0.21, y: 0.345, w: 0.07, h: 0.07}, SEC_BUTTON_COL, "?", &cam, font, 0.05) && handle_mouse {
 					ret = Some(Self::ModeInfo(GameMode::Learn));
 					assets.play_sound(FORWARD);
 				}
-				if button(&Rect{x: 0.2, y: 0.51, w: 0.08, h: 0.08}, SEC_BUTTON_COL, "?", &cam, font, 0.05) && handle_mouse {
+				if button(&Rect{x: 0.21, y: 0.435, w: 0.07, h: 0.07}, SEC_BUTTON_COL, "?", &cam, font, 0.05) && handle_mouse {
 					ret = Some(Self::ModeInfo(GameMode::Serious));
 					assets.play_sound(FORWARD);
 				}
+				if button(&Rect{x: 0.21, y: 0.525, w: 0.07, h: 0.07}, SEC_BUTTON_COL, "?", &cam, font, 0.05) && handle_mouse {
+					ret = Some(Self::ModeInfo(GameMode::Versus));
+					assets.play_sound(FORWARD);
+				}
+				if button(&Rect{x: 0.72, y: 0.345, w: 0.07, h: 0.07}, SEC_BUTTON_COL, "?", &cam, font, 0.05) && handle_mouse {
+					ret = Some(Self::ModeInfo(GameMode::Zen));
+					assets.play_sound(FORWARD);
+				}
+				if button(&Rect{x: 0.72, y: 0.435, w: 0.07, h: 0.07}, SEC_BUTTON_COL, "?", &cam, font, 0.05) && handle_mouse {
+					ret = Some(Self::ModeInfo(GameMode::TimeAttack));
+					assets.play_sound(FORWARD);
+				}
 
-				if button(&Rect{x: 0.3, y: 0.7, w: 0.4, h: 0.1}, SEC_BUTTON_COL, "HIGHSCORES", &cam, font, 0.05) && handle_mouse {
-					ret = Some(State::Highscores);
+				if button(&Rect{x: 0.3, y: 0.61, w: 0.4, h: 0.08}, PRI_BUTTON_COL, "CAMPAIGN", &cam, font, 0.06) && handle_mouse {
+					ret = Some(State::Campaign(0.0));
+					assets.play_sound(FORWARD);
+				}
+				if button(&Rect{x: 0.3, y: 0.7, w: 0.4, h: 0.08}, PRI_BUTTON_COL, "PACKS", &cam, font, 0.06) && handle_mouse {
+					ret = Some(State::Packs(Assets::load_packs(), 0.0));
+					assets.play_sound(FORWARD);
+				}
+
+				if button(&Rect{x: 0.3, y: 0.8, w: 0.4, h: 0.09}, SEC_BUTTON_COL, "HIGHSCORES", &cam, font, 0.05) && handle_mouse {
+					ret = Some(State::Highscores(0.0));
 					assets.play_sound(FORWARD);
 				}
 
-				if button(&Rect{x: 0.3, y: 0.81, w: 0.4, h: 0.1}, SEC_BUTTON_COL, "SETTINGS", &cam, font, 0.05) && handle_mouse {
+				if button(&Rect{x: 0.3, y: 0.9, w: 0.4, h: 0.09}, SEC_BUTTON_COL, "SETTINGS", &cam, font, 0.05) && handle_mouse {
 					use crate::cell_state::CellState::*;
 					let board = Board { 
 						id: 0,
 						is_won: false, 
 						is_valid: true, 
-						size: 4, 
+						width: 4, 
+						height: 4, 
+						rules: Rules::default(),
 						map: vec![
 							vec![True(false), True(false), None, None],
 							vec![None, False(false), False(false), False(false)],
 							vec![True(false), None, True(false), None],
 							vec![False(false), False(false), None, True(false)],
 						],
-						error: [Option::Some((1, 1, 3, 1)), Option::None],
+						constraints: vec![vec![[Constraint::None; 2]; 4]; 4],
+						errors: vec![Violation { rule: Rule::Triplet, regions: vec![(1, 1, 3, 1)] }],
 						error_time: 0.0,
 						hint: Some((2, 3)),
 						show_locked: Option::None,
@@ -123,6 +409,7 @@ impl State {
 						generation_end_time: -1.0,
 						generation_duration: -1.0,
 						is_generating: false,
+						progress: Option::None,
 					}; 
 					ret = Some(State::Settings(board));
 					assets.play_sound(FORWARD);
@@ -133,7 +420,7 @@ impl State {
 					assets.play_sound(FORWARD);
 				}
 			}
-			Self::DifficultyChoice(board, next, size) => {
+			Self::DifficultyChoice(board, next, width, height, rules) => {
 
 				let display_rect = rect_circumscribed_on_rect(Rect { x: -0.7, y: -0.2, w: 2.4, h: 2.6 }, screen_width()/screen_height());
 				let camera = Camera2D::from_display_rect(display_rect);
@@ -146,35 +433,98 @@ impl State {
 				let camera = Camera2D::from_display_rect(display_rect);
 				set_camera(&camera);
 
-				draw_centered_text(vec2(0.5, 0.5), "board size:", font, 0.1);
+				draw_centered_text(vec2(0.5, 0.45), "board size:", font, 0.1);
+
+				let old_size = (*width, *height);
+				let old_rules = *rules;
 
-				let old_size = *size;
-				let mut val = (*size as f32 - 2.0)/18.0;
-				slider(&mut val, 0.0, 1.0, vec2(0.2, 0.65), 0.6, SLIDER_COL, &camera);
-				*size = ((val*18.0 + 2.0)/2.0).round() as usize * 2;
+				let toggle_col = |on: bool| if on {PRI_BUTTON_COL} else {SEC_BUTTON_COL};
+				if button(&Rect{x: -0.08, y: 0.0, w: 0.3, h: 0.1}, toggle_col(rules.constraints), "Binairo+", &camera, font, 0.05) {
+					rules.constraints = !rules.constraints;
+					assets.play_sound(FORWARD);
+				}
+				if button(&Rect{x: -0.08, y: 0.11, w: 0.3, h: 0.1}, toggle_col(rules.odd_sizes), "Odd sizes", &camera, font, 0.05) {
+					rules.odd_sizes = !rules.odd_sizes;
+					assets.play_sound(FORWARD);
+				}
+				if button(&Rect{x: -0.08, y: 0.22, w: 0.3, h: 0.1}, toggle_col(rules.mask != Mask::None), rules.mask.as_str(), &camera, font, 0.05) {
+					rules.mask = rules.mask.next();
+					assets.play_sound(FORWARD);
+				}
+				if button(&Rect{x: 0.78, y: 0.0, w: 0.3, h: 0.1}, toggle_col(rules.wrap), "Wrap", &camera, font, 0.05) {
+					rules.wrap = !rules.wrap;
+					assets.play_sound(FORWARD);
+				}
+				if let GameMode::Serious = next {
+					if button(&Rect{x: 0.78, y: 0.11, w: 0.3, h: 0.1}, toggle_col(assets.persistance.strict), "Strict", &camera, font, 0.05) {
+						assets.persistance.strict = !assets.persistance.strict;
+						assets.play_sound(FORWARD);
+					}
+				}
+
+				// odd sizes snap back to even ones once they are turned off
+				let step = if rules.odd_sizes {1} else {2};
+				let mut val = (*width as f32 - 2.0)/18.0;
+				slider(&mut val, 0.0, 1.0, vec2(0.2, 0.58), 0.6, SLIDER_COL, &camera);
+				*width = ((val*18.0 + 2.0)/step as f32).round() as usize * step;
+				let mut val = (*height as f32 - 2.0)/18.0;
+				slider(&mut val, 0.0, 1.0, vec2(0.2, 0.7), 0.6, SLIDER_COL, &camera);
+				*height = ((val*18.0 + 2.0)/step as f32).round() as usize * step;
+
+				// keep the dimension that was just changed and move the other one to the nearest one that can be generated
+				if !Board::is_feasible(*width, *height, *rules) {
+					if *width != old_size.0 {
+						*height = (2..=20).step_by(step).filter(|h| Board::is_feasible(*width, *h, *rules)).min_by_key(|h| h.abs_diff(*height)).unwrap();
+					}
+					else {
+						*width = (2..=20).step_by(step).filter(|w| Board::is_feasible(*w, *height, *rules)).min_by_key(|w| w.abs_diff(*width)).unwrap();
+					}
+				}
 				
-				draw_centered_text(vec2(0.5, 0.8), format!("{size}").as_str(), font, 0.1);
+				draw_centered_text(vec2(0.1, 0.58), "w", font, 0.07);
+				draw_centered_text(vec2(0.1, 0.7), "h", font, 0.07);
+				draw_centered_text(vec2(0.5, 0.83), format!("{width} x {height}").as_str(), font, 0.1);
 
-				if old_size != *size {
-					*board = Board::new(*size, 0, false);
-					board.generate_fraction(0.6);
+				if old_size != (*width, *height) || old_rules != *rules {
+					*board = Board::new_preview(*width, *height, *rules);
 				}
 
 				if button(&Rect{x: 0.35, y: 0.95, w: 0.3, h: 0.1}, PRI_BUTTON_COL, "PLAY", &camera, font, 0.08) {
-					assets.persistance.game_size = *size;
+					assets.persistance.game_width = *width;
+					assets.persistance.game_height = *height;
+					assets.persistance.game_rules = *rules;
 					assets.persistance.save();
 					let id = assets.next_board_id;
 					assets.next_board_id += 1;
 					ret = Some(
 						match next {
-							GameMode::Sandbox => State::Sandbox(Board::new(*size, id, false)),
+							GameMode::Sandbox => {
+								let mut board = Board::new(*width, *height, *rules, id, false);
+								board.place_mask(0.12);
+								State::Sandbox(board)
+							},
 							GameMode::Learn => {
-								assets.sender.send((*size, GameMode::Learn, id)).unwrap();
-								State::Learn(Board::new(*size, id, true))
+								assets.order_board(*width, *height, *rules, GameMode::Learn, id);
+								let board = Board::new(*width, *height, *rules, id, true);
+								let replay = recording("learn", &board);
+								State::Learn(board, replay)
 							},
 							GameMode::Serious => {
-								assets.sender.send((*size, GameMode::Serious, id)).unwrap();
-								State::Serious(Board::new(*size, id, true), get_time() as f32 + 1.5, None, 0)
+								assets.order_board(*width, *height, *rules, GameMode::Serious, id);
+								let board = Board::new(*width, *height, *rules, id, true);
+								let replay = recording("serious", &board);
+								State::Serious(SeriousGame::new(board, assets.persistance.strict.then_some(Strict::default()), replay, None))
+							}
+							GameMode::Versus => {
+								assets.order_board(*width, *height, *rules, GameMode::Versus, id);
+								let board = Board::new(*width, *height, *rules, id, true);
+								State::Versus(vec![Player::new(&board), Player::new(&board)], get_time() as f32 + 1.5, 0, None)
+							}
+							GameMode::TimeAttack => time_attack(*width, *height, *rules, assets),
+							GameMode::Zen => {
+								let id = order_new_board(*width, *height, *rules, GameMode::Zen, assets);
+								let next = order_new_board(*width, *height, *rules, GameMode::Zen, assets);
+								State::Zen(ZenGame { board: Board::new(*width, *height, *rules, id, true), next: None, next_id: next, solved: 0, last_move: get_time() as f32, solved_at: None })
 							}
 						}
 					);
@@ -183,7 +533,9 @@ impl State {
 
 				if button(&Rect { x: 0.8, y: -0.15, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Back", &camera, font, 0.06) {
 					ret = Some(State::MainMenu);
-					assets.persistance.game_size = *size;
+					assets.persistance.game_width = *width;
+					assets.persistance.game_height = *height;
+					assets.persistance.game_rules = *rules;
 					assets.persistance.save();
 					assets.play_sound(BACKWARD);
 				}
@@ -191,11 +543,13 @@ impl State {
 			Self::Sandbox(board) => {
 				
 				// (0,0) to (1,1) is the board. Depending on the aspect ratio: vertical will have space at the bottom and horizontal will have space to the left for some ui. Also allocate space at the top for exit and timer
+				// boards wider than tall only span (0,0) to (1,h), so the vertical layout is shifted up by the missing height
+				let board_h = board.rect().h;
 				let allocated_rect = if screen_width() / screen_height() > 1.0 {
 					Rect{x: -0.8, y: -0.2, w: 1.9, h: 1.3}
 				}
 				else {
-					Rect{x: -0.1, y: -0.2, w: 1.2, h: 1.6}
+					Rect{x: -0.1, y: -0.2, w: 1.2, h: board_h + 0.6}
 				};
 				let display_rect = rect_circumscribed_on_rect(allocated_rect, screen_width()/screen_height());
 				let camera = Camera2D::from_display_rect(display_rect);
@@ -223,11 +577,11 @@ impl State {
 				}
 				
 				if handle_mouse {
-					board.handle_mouse(&camera, &assets);
+					board.handle_mouse(&camera, assets);
 				}
-				board.draw_errors(Some(&assets));
+				board.draw_errors(Some(assets));
 				board.draw_hint();
-				board.draw(&assets);
+				board.draw(assets);
 				
 				let scale = 0.04;
 				let buttons = if screen_width() / screen_height() > 1.0 {
@@ -256,9 +610,9 @@ impl State {
 					let c2 = 0.25;
 					let c3 = 0.41;
 					let c4 = 0.71;
-					let r1 = 1.05;
-					let r2 = 1.16;
-					let r3 = 1.27;
+					let r1 = board_h + 0.05;
+					let r2 = board_h + 0.16;
+					let r3 = board_h + 0.27;
 					[
 					Rect{x: c1, y: r1, w,     h},
 					Rect{x: c1, y: r2, w: w2, h}, 
@@ -275,17 +629,23 @@ impl State {
 				
 				if button(&buttons[0], PRI_BUTTON_COL, "Generate", &camera, font, scale) && handle_mouse {
 					assets.play_sound(FORWARD);
-					board.generate_valid();
+					board.generate_solution();
+					if board.rules.constraints {
+						board.place_constraints(0.3);
+					}
 				}
 				if button(&buttons[1], PRI_BUTTON_COL, "Purge some", &camera, font, scale) && handle_mouse {
 					assets.play_sound(FORWARD);
 					board.degenerate();
-					board.verify_board();
+					board.verify_board_now();
 				}
 				if button(&buttons[2], PRI_BUTTON_COL, "Purge all", &camera, font, scale) && handle_mouse {
 					assets.play_sound(FORWARD);
 					board.purge_redundancies();
-					board.verify_board();
+					if board.rules.constraints {
+						board.purge_constraints();
+					}
+					board.verify_board_now();
 				}
 				if button(&buttons[3], PRI_BUTTON_COL, "Clear", &camera, font, scale) && handle_mouse {
 					assets.play_sound(FORWARD);
@@ -294,45 +654,54 @@ impl State {
 				if button(&buttons[4], PRI_BUTTON_COL, "Surround", &camera, font, scale) && handle_mouse {
 					assets.play_sound(FORWARD);
 					board.surround_doubles();
-					board.verify_board();
+					board.verify_board_now();
 				}
 				if button(&buttons[5], PRI_BUTTON_COL, "De-Surround", &camera, font, scale) && handle_mouse {
 					assets.play_sound(FORWARD);
 					board.desurround_doubles(1.0);
-					board.verify_board();
+					board.verify_board_now();
 				}
 				if button(&buttons[6], PRI_BUTTON_COL, "Separate", &camera, font, scale) && handle_mouse {
 					assets.play_sound(FORWARD);
 					board.separate_triples();
-					board.verify_board();
+					board.verify_board_now();
 				}
 				if button(&buttons[7], PRI_BUTTON_COL, "De-Separate", &camera, font, scale) && handle_mouse {
 					assets.play_sound(FORWARD);
 					board.deseparate_triples(1.0);
-					board.verify_board();
+					board.verify_board_now();
 				}
 				if button(&buttons[8], PRI_BUTTON_COL, "Fill", &camera, font, scale) && handle_mouse {
 					assets.play_sound(FORWARD);
 					board.fill_rows();
-					board.verify_board();
+					board.verify_board_now();
 				}
 				if button(&buttons[9], PRI_BUTTON_COL, "De-Fill", &camera, font, scale) && handle_mouse {
 					assets.play_sound(FORWARD);
 					board.defill_rows(1.0);
-					board.verify_board();
+					board.verify_board_now();
 				}
 				
 				if button(&Rect { x: 0.0, y: -0.15, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Hint", &camera, font, 0.06) && handle_mouse {
-					board.generate_hint(&assets);
+					board.generate_hint(assets);
+				}
+				if button(&Rect { x: 0.22, y: -0.15, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Print", &camera, font, 0.06) && handle_mouse {
+					match Assets::export_sheet(board) {
+						Ok(_) => assets.play_sound(FORWARD),
+						Err(e) => {
+							eprintln!("can't export the board: {e}");
+							assets.play_sound(BACKWARD);
+						}
+					}
 				}
 				if button(&Rect { x: 0.8, y: -0.15, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Exit", &camera, font, 0.06) && handle_mouse {
 					assets.play_sound(BACKWARD);
 					ret = Some(State::ExitConfirmation(Box::new(self.clone())));
 				}
 			}
-			Self::Learn(board) => {
+			Self::Learn(board, replay) => {
 				
-				let display_rect = rect_circumscribed_on_rect(Rect { x: -0.1, y: -0.2, w: 1.2, h: 1.3 }, screen_width()/screen_height());
+				let display_rect = rect_circumscribed_on_rect(Rect { x: -0.1, y: -0.2, w: 1.2, h: board.rect().h + 0.3 }, screen_width()/screen_height());
 				let camera = Camera2D::from_display_rect(display_rect);
 				set_camera(&camera);
 				
@@ -357,34 +726,49 @@ impl State {
 					});
 				}
 				
+				let before = board.map.clone();
 				if handle_mouse {
-					board.handle_mouse(&camera, &assets);
-				}				
-				board.draw_errors(Some(&assets));
+					board.handle_mouse(&camera, assets);
+					if is_key_pressed(KeyCode::Z) {
+						undo(board, replay, get_time() as f32 - replay.start);
+					}
+				}
+				replay.record_changes(get_time() as f32 - replay.start, &before, board);
+				board.draw_errors(Some(assets));
 				board.draw_hint();
-				board.draw(&assets);
+				board.draw(assets);
 				
 				
 				if handle_mouse {
 					if button(&Rect { x: 0.0, y: -0.15, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Hint", &camera, font, 0.06) && handle_mouse {
-						board.generate_hint(&assets);
+						board.generate_hint(assets);
+						if let Some((x, y)) = board.hint {
+							replay.record(get_time() as f32 - replay.start, Action::Hint(x, y));
+						}
+					}
+					if button(&Rect { x: 0.22, y: -0.15, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Undo", &camera, font, 0.06) && handle_mouse {
+						undo(board, replay, get_time() as f32 - replay.start);
 					}
 					if button(&Rect { x: 0.8, y: -0.15, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Exit", &camera, font, 0.06) && handle_mouse {
-						ret = Some(State::ExitConfirmation(Box::new(State::Learn(board.clone()))));
+						ret = Some(State::ExitConfirmation(Box::new(State::Learn(board.clone(), replay.clone()))));
 						assets.play_sound(BACKWARD);
 					}
 				
 					if board.is_won {
-						ret = Some(State::EndScreen(Box::new(State::Learn(board.clone())), None));
+						replay.finish(get_time() as f32 - replay.start, board);
+						if let Err(e) = assets.save_replay(replay) {
+							eprintln!("can't save the replay: {e}");
+						}
+						ret = Some(State::EndScreen(Box::new(State::Learn(board.clone(), replay.clone())), None));
 					}
 				}
 			}
-			Self::Serious(board, start_time, finished_time, sounds) => {
-				let display_rect = rect_circumscribed_on_rect(Rect { x: -0.1, y: -0.2, w: 1.2, h: 1.3 }, screen_width()/screen_height());
+			Self::Serious(game) => {
+				let display_rect = rect_circumscribed_on_rect(Rect { x: -0.1, y: -0.2, w: 1.2, h: game.board.rect().h + if game.ghost.is_some() {0.4} else {0.3} }, screen_width()/screen_height());
 				let camera = Camera2D::from_display_rect(display_rect);
 				set_camera(&camera);
 				
-				let status_color = if board.is_won {Some(GREEN)} else if !board.has_nones() && !board.is_valid {Some(RED)} else {None};
+				let status_color = if game.board.is_won {Some(GREEN)} else if !game.board.has_nones() && !game.board.is_valid {Some(RED)} else {None};
 				
 				if let Some(c) = status_color {
 					let w = 0.2;
@@ -404,48 +788,142 @@ impl State {
 					});
 				}
 				
-				if board.is_won && finished_time.is_none() {
-					let time = get_time() as f32 - *start_time;
-					*finished_time = Some(time);
-					let (is_highscore, prev_time) = assets.persistance.insert_highscore(board.size, time);
-					ret = Some(State::EndScreen(Box::new(State::Serious(board.clone(), *start_time, Some(time), *sounds)), if is_highscore {Some((time, prev_time))} else {None}));
+				if game.board.is_won && game.finished_time.is_none() {
+					let time = get_time() as f32 - game.start_time;
+					game.finished_time = Some(time);
+					let (is_highscore, prev_time) = assets.persistance.insert_highscore(game.board.width, game.board.height, game.board.rules, time, game.strict.as_ref().map(|s| s.strikes));
+					game.replay.finish(time, &game.board);
+					if let Err(e) = assets.save_replay(&game.replay) {
+						eprintln!("can't save the replay: {e}");
+					}
+					ret = Some(State::EndScreen(Box::new(State::Serious(game.clone())), if is_highscore {Some((time, prev_time))} else {None}));
 				}
-				if get_time() as f32 > *start_time && !board.is_generating {
-					let passed = if let Some(t) = *finished_time {t} else {get_time() as f32 - *start_time};
+				if get_time() as f32 > game.start_time && !game.board.is_generating {
+					let passed = if let Some(t) = game.finished_time {t} else {get_time() as f32 - game.start_time};
 					let mut str = format!("0{:.2}s", passed);
 					if passed >= 10.0 {str = str[1..].to_owned();}
 					
 					for (i, c) in str.chars().enumerate() {
 						draw_centered_text_stable(vec2(i as f32 * 0.07 + 0.04, -0.1), [c].iter().collect::<String>().as_str(), "0", font, 0.1);
 					}
+
+					// racing the best run: how far it had got at this time, and how much earlier it had as many cells filled in
+					if let Some(ghost) = &game.ghost {
+						let filled = Replay::filled(&game.board.map);
+						let (ghost_filled, total, delta) = ghost.race(passed, filled, game.finished_time.is_some());
+						let y = game.board.rect().h + 0.13;
+						for (i, (count, col)) in [(filled, WHITE), (ghost_filled, Color { a: 0.4, ..WHITE })].into_iter().enumerate() {
+							let y = y + i as f32 * 0.04;
+							draw_round_rect(0.0, y, 1.0, 0.025, 0.01, POPUP_EDGE_COL);
+							draw_round_rect(0.0, y, (count as f32 / total as f32).min(1.0), 0.025, 0.01, col);
+						}
+						draw_centered_text_color(vec2(0.5, y + 0.11), format!("best run: {:.2}s", ghost.duration()).as_str(), font, 0.035, GRAY);
+
+						if let Some(delta) = delta {
+							// right of the timer, above it when the timer gets too long for the pause button
+							let pos = if str.len() < 7 {vec2(str.len() as f32 * 0.07 + 0.06, -0.1)} else {vec2(0.3, -0.17)};
+							draw_centered_text_color(pos, format!("{delta:+.2}s").as_str(), font, 0.045, if delta < 0.0 {GREEN} else {RED});
+						}
+					}
 				}
 				
-				if handle_mouse && get_time() as f32 > *start_time {
-					board.handle_mouse(&camera, &assets);
+				let game_time = get_time() as f32 - game.start_time;
+				if handle_mouse && game_time > 0.0 && game.finished_time.is_none() && is_key_pressed(KeyCode::Z) {
+					undo(&mut game.board, &mut game.replay, game_time);
+				}
+				let before = game.board.map.clone();
+				if handle_mouse && get_time() as f32 > game.start_time {
+					game.board.handle_mouse(&camera, assets);
+				}
+				game.replay.record_changes(game_time, &before, &game.board);
+				game.board.draw(assets);
+
+				if let Some(Strict { solution: Some(solution), strikes, pending }) = game.strict.as_mut() {
+					let now = get_time() as f32;
+					let wrong = |x: usize, y: usize| game.board.map[y][x].is_filled() && game.board.map[y][x] != solution.map[y][x];
+					pending.retain(|(x, y, _)| wrong(*x, *y));
+					for (y, row) in before.iter().enumerate() {
+						for (x, cell) in row.iter().enumerate() {
+							if game.board.map[y][x] != *cell && wrong(x, y) {
+								pending.push((x, y, now));
+							}
+						}
+					}
+					let settled = pending.iter().filter(|(_, _, since)| now - since >= STRIKE_SETTLE).count() as u32;
+					pending.retain(|(_, _, since)| now - since < STRIKE_SETTLE);
+					if settled > 0 && game.finished_time.is_none() {
+						let settled = settled.min(MAX_STRIKES - *strikes);
+						*strikes += settled;
+						game.start_time -= STRIKE_PENALTY * settled as f32;
+						assets.play_sound(ERROR);
+					}
+
+					for i in 0..MAX_STRIKES {
+						let x = 0.5 + (i as f32 - (MAX_STRIKES - 1) as f32 / 2.0) * 0.08;
+						draw_circle(x, game.board.rect().h + 0.05, 0.02, if i < *strikes {RED} else {POPUP_EDGE_COL});
+					}
+				}
+				if game.strict.as_ref().is_some_and(|s| s.strikes >= MAX_STRIKES) && game.finished_time.is_none() {
+					let time = get_time() as f32 - game.start_time;
+					game.finished_time = Some(time);
+					ret = Some(State::EndScreen(Box::new(State::Serious(game.clone())), None));
 				}
-				board.draw(&assets);
 				
-				if button(&Rect { x: 0.8, y: -0.15, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Exit", &camera, font, 0.06) && handle_mouse && get_time() as f32 > *start_time {
+				// the clock stops while the exit confirmation is up
+				let running = get_time() as f32 > game.start_time && !game.board.is_generating && game.finished_time.is_none();
+				let this = State::Serious(game.clone());
+				if button(&Rect { x: 0.8, y: -0.15, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Exit", &camera, font, 0.06) && handle_mouse && get_time() as f32 > game.start_time {
 					assets.play_sound(BACKWARD);
-					ret = Some(State::ExitConfirmation(Box::new(State::Serious(board.clone(), *start_time, *finished_time, *sounds))))
+					ret = Some(State::ExitConfirmation(Box::new(if running {State::Paused(Box::new(this), get_time() as f32)} else {this})))
 				}
-
-				if *start_time > get_time() as f32 && !board.is_generating {
-					let countdown = ((*start_time - get_time() as f32) / 1.5 * 4.0).floor();
-					let t = 1.0 - ((*start_time - get_time() as f32) / 1.5 * 4.0).fract();
-					draw_rectangle(display_rect.x, display_rect.y, display_rect.w, display_rect.h, Color { r: 0.0, g: 0.0, b: 0.0, a: 0.8 });
-					let col = Color {r: 1.0, g: 1.0, b: 1.0, a: (t*t*3.0).min(1.0)};
-					if countdown > 0.0 {
-						draw_centered_text_color(display_rect.center(), format!("{countdown}").as_str(), font, 0.4-t*0.2, col);
+				else if running && handle_mouse {
+					if button(&Rect { x: 0.55, y: -0.15, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Pause", &camera, font, 0.06) || is_key_pressed(KeyCode::Escape) || is_key_pressed(KeyCode::P) {
+						assets.play_sound(BACKWARD);
+						ret = Some(State::Paused(Box::new(this), get_time() as f32));
 					}
-					if countdown < 3.0 {
-						draw_centered_text(display_rect.center(), format!("{}", countdown+1.0).as_str(), font, 0.2-t*0.2);
+					// the stall doesn't count as playing time
+					else if window_was_hidden() {
+						ret = Some(State::Paused(Box::new(this), get_time() as f32 - get_frame_time()));
 					}
-					
-					if countdown > 0.0 && 3.0 - (*sounds as f32) >= countdown {
-						*sounds += 1;
-						assets.play_sound(TICK);
+				}
+
+				if game.start_time > get_time() as f32 && !game.board.is_generating {
+					draw_countdown(display_rect, game.start_time, &mut game.sounds, assets);
+				}
+			}
+			Self::Paused(inner_state, since) => {
+				// the board isn't drawn, so it can't be studied while the clock is stopped
+				let allocated_rect = Rect {x: 0.0, y: 0.0, w: 1.0, h: 1.0};
+				let display_rect = rect_circumscribed_on_rect(allocated_rect, screen_width()/screen_height());
+
+				let cam = Camera2D::from_display_rect(display_rect);
+				set_camera(&cam);
+
+				draw_rectangle(display_rect.x, display_rect.y, display_rect.w, display_rect.h, Color { r: 0.0, g: 0.0, b: 0.0, a: 0.8 });
+
+				let m = 0.01;
+				draw_round_rect(0.2-m, 0.2-m, 0.6+2.0*m, 0.6+2.0*m, 0.05+m, POPUP_EDGE_COL);
+				draw_round_rect(0.2, 0.2, 0.6, 0.6, 0.05, POPUP_COL);
+
+				draw_centered_text(allocated_rect.center() - vec2(0.0, 0.2), "Paused", font, 0.1);
+				if let State::Serious(game) = &**inner_state {
+					draw_centered_text_color(allocated_rect.center() - vec2(0.0, 0.08), format!("{:.2}s", *since - game.start_time).as_str(), font, 0.06, WHITE);
+				}
+
+				let resume = button(&Rect { x: 0.3, y: 0.5, w: 0.4, h: 0.1 }, PRI_BUTTON_COL, "Resume", &cam, font, 0.07) || is_key_pressed(KeyCode::Escape) || is_key_pressed(KeyCode::P);
+				if resume && handle_mouse {
+					assets.play_sound(FORWARD);
+					let mut inner = (**inner_state).clone();
+					if let State::Serious(game) = &mut inner {
+						let pause = get_time() as f32 - *since;
+						game.start_time += pause;
+						game.paused += pause;
 					}
+					ret = Some(inner);
+				}
+				if button(&Rect { x: 0.3, y: 0.63, w: 0.4, h: 0.1 }, SEC_BUTTON_COL, "Exit", &cam, font, 0.07) && handle_mouse {
+					assets.play_sound(BACKWARD);
+					ret = Some(State::ExitConfirmation(Box::new(self.clone())));
 				}
 			}
 			Self::ExitConfirmation(inner_state) => {
@@ -489,56 +967,139 @@ impl State {
 				draw_round_rect(0.2-m, 0.1-m, 0.6+2.0*m, 0.8+2.0*m, 0.05+m, POPUP_EDGE_COL);
 				draw_round_rect(0.2, 0.1, 0.6, 0.8, 0.05, POPUP_COL);
 				
-				draw_centered_text(allocated_rect.center() - vec2(0.0, 0.3), "Finished!", font, 0.1);
+				let struck_out = matches!(&**inner_state, State::Serious(SeriousGame { strict: Some(strict), .. }) if strict.strikes >= MAX_STRIKES);
+				let title = match &**inner_state {
+					State::Versus(_, _, _, Some((winner, _))) => format!("Player {} wins!", winner + 1),
+					State::TimeAttack(..) => "Time's up!".to_string(),
+					_ if struck_out => "Out of strikes!".to_string(),
+					_ => "Finished!".to_string(),
+				};
+				draw_centered_text(allocated_rect.center() - vec2(0.0, 0.3), title.as_str(), font, 0.1);
 				
 				match highscore {
 					None => {
 						match &**inner_state {
-							State::Learn(_) => {
+							State::Learn(..) => {
 								draw_centered_text_color(allocated_rect.center(), "(No scores in Learn mode)", font, 0.03, GRAY);
 							}
-							State::Serious(board, _, time, _) => {
+							State::Serious(SeriousGame { board, finished_time: time, strict, .. }) => {
+								if let (Some(time), false) = (time, struck_out) {
+									draw_centered_text_color(allocated_rect.center() - vec2(0.0, 0.1), format!("time: {:.2}s", time).as_str(), font, 0.08, WHITE);
+								}
+								if let Some(highscore) = assets.persistance.highscore(board.width, board.height, board.rules, strict.is_some()) {
+									draw_centered_text_color(allocated_rect.center(), format!("highscore: {:.2}s", highscore).as_str(), font, 0.05, ORANGE);
+								}
+							}
+							State::Versus(_, _, _, Some((_, time))) => {
+								let [first, second] = assets.persistance.wins();
+								draw_centered_text_color(allocated_rect.center() - vec2(0.0, 0.1), format!("time: {:.2}s", time).as_str(), font, 0.08, WHITE);
+								draw_centered_text_color(allocated_rect.center(), format!("wins {first} : {second}").as_str(), font, 0.05, ORANGE);
+							}
+							State::TimeAttack(TimeAttackGame { board, solved, .. }) => {
+								draw_centered_text_color(allocated_rect.center() - vec2(0.0, 0.1), format!("boards: {solved}").as_str(), font, 0.08, WHITE);
+								if let Some(best) = assets.persistance.time_attack_best(board.width, board.height, board.rules) {
+									draw_centered_text_color(allocated_rect.center(), format!("best: {best}").as_str(), font, 0.05, ORANGE);
+								}
+							}
+							State::CampaignLevel(_, level, _, time, _, _) => {
 								draw_centered_text_color(allocated_rect.center() - vec2(0.0, 0.1), format!("time: {:.2}s", time.unwrap()).as_str(), font, 0.08, WHITE);
-								draw_centered_text_color(allocated_rect.center(), format!("highscore: {:.2}s", assets.persistance.highscores[board.size/2 - 1].unwrap()).as_str(), font, 0.05, ORANGE);
+								draw_centered_text_color(allocated_rect.center(), format!("best: {:.2}s", assets.persistance.campaign[*level].best_time).as_str(), font, 0.05, ORANGE);
 							}
 							_ => {}
 						}
 					}
 					Some((new, previous)) => {
+						// time attack counts boards instead of seconds
+						let score = |score: f32| if let State::TimeAttack(..) = &**inner_state {format!("{score} boards")} else {format!("{score:.2}s")};
 						draw_centered_text_color(allocated_rect.center() - vec2(0.0, 0.2), "High Score!", font, 0.09, ORANGE);
-						draw_centered_text_color(allocated_rect.center() - vec2(0.0, 0.07), format!("{:.2}s", new).as_str(), font, 0.08, ORANGE);
+						draw_centered_text_color(allocated_rect.center() - vec2(0.0, 0.07), score(*new).as_str(), font, 0.08, ORANGE);
 						if let Some(previous) = previous {
-							draw_centered_text_color(allocated_rect.center() + vec2(0.0, 0.0), format!("{:.2}s", previous).as_str(), font, 0.05, WHITE);
+							draw_centered_text_color(allocated_rect.center() + vec2(0.0, 0.0), score(*previous).as_str(), font, 0.05, WHITE);
 						}
 						
 					}
 				}
 				
-				if button(&Rect { x: 0.25, y: 0.6, w: 0.5, h: 0.1 }, PRI_BUTTON_COL, "Play Again", &cam, font, 0.07) {
+				// pauses don't count towards the time, but they are shown
+				if let State::Serious(SeriousGame { paused, strict, .. }) = &**inner_state {
+					if let Some(strict) = strict {
+						let text = format!("strikes: {}/{MAX_STRIKES} (+{:.0}s)", strict.strikes, strict.strikes as f32 * STRIKE_PENALTY);
+						draw_centered_text_color(allocated_rect.center() + vec2(0.0, 0.035), text.as_str(), font, 0.04, if strict.strikes > 0 {RED} else {GRAY});
+					}
+					if *paused > 0.0 {
+						draw_centered_text_color(allocated_rect.center() + vec2(0.0, 0.07), format!("paused for {:.2}s", paused).as_str(), font, 0.04, GRAY);
+					}
+				}
+
+				// stars earned by this run
+				if let State::CampaignLevel(board, _, _, Some(time), hints, errors) = &**inner_state {
+					let stars = [(*time <= par_time(board), "time"), (*hints == 0, "no hints"), (*errors == 0, "no errors")];
+					for (i, (star, label)) in stars.iter().enumerate() {
+						let center = allocated_rect.center() + vec2((i as f32 - 1.0) * 0.15, 0.03);
+						draw_circle(center.x, center.y, 0.025, if *star {ORANGE} else {POPUP_EDGE_COL});
+						draw_centered_text_color(center + vec2(0.0, 0.05), label, font, 0.03, WHITE);
+					}
+				}
+
+				let next_level = match &**inner_state {
+					State::CampaignLevel(_, level, _, _, _, _) => Some((*level + 1).min(assets.campaign.len() - 1)),
+					_ => None,
+				};
+				let again = if next_level.is_some() {"Next Level"} else {"Play Again"};
+				if button(&Rect { x: 0.25, y: 0.6, w: 0.5, h: 0.1 }, PRI_BUTTON_COL, again, &cam, font, 0.07) {
 					assets.play_sound(FORWARD);
 					let id = assets.next_board_id;
 					assets.next_board_id += 1;
 					match &**inner_state {
-						State::Serious(b, _, _, _) => {
-							assets.sender.send((b.size, GameMode::Serious, id)).unwrap();
-							ret = Some(State::Serious(Board::new(b.size, id, true), get_time() as f32 + 1.5, None, 0));
+						State::Serious(SeriousGame { board: b, strict, .. }) => {
+							assets.order_board(b.width, b.height, b.rules, GameMode::Serious, id);
+							let board = Board::new(b.width, b.height, b.rules, id, true);
+							let replay = recording("serious", &board);
+							ret = Some(State::Serious(SeriousGame::new(board, strict.as_ref().map(|_| Strict::default()), replay, None)));
+						}
+						State::Versus(players, ..) => {
+							let b = &players[0].board;
+							assets.order_board(b.width, b.height, b.rules, GameMode::Versus, id);
+							let board = Board::new(b.width, b.height, b.rules, id, true);
+							ret = Some(State::Versus(vec![Player::new(&board), Player::new(&board)], get_time() as f32 + 1.5, 0, None));
 						}
-						State::Learn(b) => {
-							assets.sender.send((b.size, GameMode::Learn, id)).unwrap();
-							let board = Board::new(b.size, id, true);
-							ret = Some(State::Learn(board));
+						State::TimeAttack(TimeAttackGame { board: b, .. }) => ret = Some(time_attack(b.width, b.height, b.rules, assets)),
+						State::Learn(b, _) => {
+							assets.order_board(b.width, b.height, b.rules, GameMode::Learn, id);
+							let board = Board::new(b.width, b.height, b.rules, id, true);
+							let replay = recording("learn", &board);
+							ret = Some(State::Learn(board, replay));
+						}
+						State::CampaignLevel(..) => {
+							let level = next_level.unwrap();
+							let mut board = assets.campaign[level].board.clone();
+							board.id = id;
+							ret = Some(State::CampaignLevel(board, level, get_time() as f32, None, 0, 0));
 						}
 						_ => {
-							ret = Some(State::Learn(Board::new(6, id, true)));
+							let board = Board::new(6, 6, Rules::default(), id, true);
+							let replay = recording("learn", &board);
+							ret = Some(State::Learn(board, replay));
 						}
 					}
 				}
-				if button(&Rect { x: 0.25, y: 0.75, w: 0.5, h: 0.1 }, SEC_BUTTON_COL, "Back", &cam, font, 0.07) {
+				let replay = match &**inner_state {
+					State::Learn(_, replay) | State::Serious(SeriousGame { replay, .. }) if replay.finish.is_some() => Some(replay),
+					_ => None,
+				};
+				let back_w = if replay.is_some() {0.24} else {0.5};
+				if button(&Rect { x: 0.25, y: 0.75, w: back_w, h: 0.1 }, SEC_BUTTON_COL, "Back", &cam, font, 0.07) {
 					assets.play_sound(BACKWARD);
-					ret = Some(State::MainMenu);
+					ret = Some(if next_level.is_some() {State::Campaign(0.0)} else {State::MainMenu});
+				}
+				if let Some(replay) = replay {
+					if button(&Rect { x: 0.51, y: 0.75, w: 0.24, h: 0.1 }, SEC_BUTTON_COL, "Replay", &cam, font, 0.07) {
+						assets.play_sound(FORWARD);
+						ret = Some(State::Replay(replay.clone(), 0.0, 1.0, true, replay.verify()));
+					}
 				}
 			}
-			Self::Highscores => {
+			Self::Highscores(scroll) => {
 				
 				let display_rect = rect_circumscribed_on_rect(Rect { x: -0.1, y: -0.2, w: 1.2, h: 1.3 }, screen_width()/screen_height());
 				let camera = Camera2D::from_display_rect(display_rect);
@@ -548,15 +1109,87 @@ impl State {
 					assets.play_sound(BACKWARD);
 					ret = Some(State::MainMenu);
 				}
+				if button(&Rect { x: 0.0, y: -0.1, w: 0.25, h: 0.1 }, SEC_BUTTON_COL, "Replays", &camera, font, 0.06) {
+					assets.play_sound(FORWARD);
+					ret = Some(State::Replays(Assets::load_replays(), 0.0));
+				}
+				if button(&Rect { x: 0.27, y: -0.1, w: 0.25, h: 0.1 }, SEC_BUTTON_COL, "Matches", &camera, font, 0.06) {
+					assets.play_sound(FORWARD);
+					ret = Some(State::Matches(0.0));
+				}
+				if button(&Rect { x: 0.54, y: -0.1, w: 0.24, h: 0.1 }, SEC_BUTTON_COL, "Time attack", &camera, font, 0.045) {
+					assets.play_sound(FORWARD);
+					ret = Some(State::TimeAttackScores(0.0));
+				}
+
+				let max_scroll = (assets.persistance.highscores.len() as f32 * 0.1 - 1.0).max(0.0);
+				*scroll = (*scroll - mouse_wheel().1 * 0.05).clamp(0.0, max_scroll);
 
-				let mut y = 0.0;
-				for (i, time) in assets.persistance.highscores.iter().enumerate() {
-					if let Some(t) = time {
-						let size = 2*(1+i);
-						let extra_space = if size >= 20 {""} else if size >= 10 {" "} else {"   "};
-						draw_centered_text_stable(vec2(0.5, y), format!("{extra_space}{}: {:.2}s", size, t).as_str(), "0: 000.00", font, 0.09);
-						y += 0.1;
+				let mut y = -*scroll;
+				for score in assets.persistance.highscores.iter() {
+					if y > -0.05 && y < 1.05 {
+						let size = format!("{}x{}{}", score.width, score.height, score.rules.label());
+						draw_centered_text_stable(vec2(0.5, y), format!("{size:>7}: {:.2}s", score.time).as_str(), "00x00 +: 000.00", font, 0.08);
+						if let Some(strikes) = score.strikes {
+							draw_centered_text_color(vec2(0.97, y), format!("strict, {strikes}x").as_str(), font, 0.03, if strikes > 0 {RED} else {GRAY});
+						}
 					}
+					y += 0.1;
+				}
+			}
+			Self::Matches(scroll) => {
+				let display_rect = rect_circumscribed_on_rect(Rect { x: -0.1, y: -0.2, w: 1.2, h: 1.3 }, screen_width()/screen_height());
+				let camera = Camera2D::from_display_rect(display_rect);
+				set_camera(&camera);
+
+				if button(&Rect { x: 0.8, y: -0.1, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Back", &camera, font, 0.06) {
+					assets.play_sound(BACKWARD);
+					ret = Some(State::Highscores(0.0));
+				}
+				let [first, second] = assets.persistance.wins();
+				draw_centered_text(vec2(0.35, -0.05), format!("Versus {first} : {second}").as_str(), font, 0.08);
+				if assets.persistance.matches.is_empty() {
+					draw_centered_text_color(vec2(0.5, 0.4), "Finished versus games show up here", font, 0.04, GRAY);
+				}
+
+				let max_scroll = (assets.persistance.matches.len() as f32 * 0.1 - 0.9).max(0.0);
+				*scroll = (*scroll - mouse_wheel().1 * 0.05).clamp(0.0, max_scroll);
+
+				// the latest first
+				let mut y = 0.1 - *scroll;
+				for game in assets.persistance.matches.iter().rev() {
+					if y > 0.0 && y < 1.05 {
+						let size = format!("{}x{}{}", game.width, game.height, game.rules.label());
+						draw_centered_text_stable(vec2(0.5, y), format!("{size:>7}: Player {} {:.2}s", game.winner + 1, game.time).as_str(), "00x00 +: Player 0 000.00s", font, 0.07);
+					}
+					y += 0.1;
+				}
+			}
+			Self::TimeAttackScores(scroll) => {
+				let display_rect = rect_circumscribed_on_rect(Rect { x: -0.1, y: -0.2, w: 1.2, h: 1.3 }, screen_width()/screen_height());
+				let camera = Camera2D::from_display_rect(display_rect);
+				set_camera(&camera);
+
+				if button(&Rect { x: 0.8, y: -0.1, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Back", &camera, font, 0.06) {
+					assets.play_sound(BACKWARD);
+					ret = Some(State::Highscores(0.0));
+				}
+				draw_centered_text(vec2(0.35, -0.05), "Time attack", font, 0.08);
+				let scores = &assets.persistance.time_attacks;
+				if scores.is_empty() {
+					draw_centered_text_color(vec2(0.5, 0.4), "The most boards solved in five minutes show up here", font, 0.035, GRAY);
+				}
+
+				let max_scroll = (scores.len() as f32 * 0.1 - 0.9).max(0.0);
+				*scroll = (*scroll - mouse_wheel().1 * 0.05).clamp(0.0, max_scroll);
+
+				let mut y = 0.1 - *scroll;
+				for score in scores.iter() {
+					if y > 0.0 && y < 1.05 {
+						let size = format!("{}x{}{}", score.width, score.height, score.rules.label());
+						draw_centered_text_stable(vec2(0.5, y), format!("{size:>7}: {:>3} boards", score.boards).as_str(), "00x00 +: 000 boards", font, 0.07);
+					}
+					y += 0.1;
 				}
 			}
 			Self::Settings(board) => {
@@ -586,6 +1219,12 @@ impl State {
 					assets.persistance.color2 = Color { r: 0.0, g: 0.5, b: 1.0, a: 1.0 }.into();
 				}
 
+				let caption_col = if assets.persistance.image_caption {PRI_BUTTON_COL} else {SEC_BUTTON_COL};
+				if button(&Rect { x: 0.45, y: 0.5, w: 0.55, h: 0.1 }, caption_col, "Image caption", &camera, font, 0.05) {
+					assets.play_sound(FORWARD);
+					assets.persistance.image_caption = !assets.persistance.image_caption;
+				}
+
 				let mut v = assets.persistance.music_volume;
 				slider(&mut v, 0.0, 2.0, vec2(0.45, 0.15), 0.55, SLIDER_COL, &camera);
 				draw_centered_text(vec2(0.725, 0.07), "Music Volume", font, 0.07);
@@ -645,7 +1284,7 @@ impl State {
 				
 				draw_centered_text(vec2(0.5, 0.2), mode.as_str(), font, 0.1);
 				for (i, line) in mode.info().iter().enumerate() {
-					draw_centered_text(vec2(0.5, 0.4+i as f32 * 0.1), *line, font, 0.08);
+					draw_centered_text(vec2(0.5, 0.4+i as f32 * 0.1), line, font, 0.08);
 				}
 
 
@@ -655,6 +1294,657 @@ impl State {
 				}
 
 			}
+			Self::Replays(replays, scroll) => {
+				let display_rect = rect_circumscribed_on_rect(Rect { x: -0.1, y: -0.2, w: 1.2, h: 1.3 }, screen_width()/screen_height());
+				let camera = Camera2D::from_display_rect(display_rect);
+				set_camera(&camera);
+
+				if button(&Rect { x: 0.8, y: -0.1, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Back", &camera, font, 0.06) {
+					assets.play_sound(BACKWARD);
+					ret = Some(State::Highscores(0.0));
+				}
+				draw_centered_text(vec2(0.35, -0.05), "Replays", font, 0.08);
+				if replays.is_empty() {
+					draw_centered_text_color(vec2(0.5, 0.4), "Won Learn and Serious games show up here", font, 0.04, GRAY);
+				}
+
+				let max_scroll = (replays.len() as f32 * 0.12 - 1.0).max(0.0);
+				*scroll = (*scroll - mouse_wheel().1 * 0.05).clamp(0.0, max_scroll);
+
+				let mut y = 0.05 - *scroll;
+				for replay in replays.iter() {
+					if y > 0.0 && y < 1.0 {
+						match replay {
+							Ok(replay) => {
+								let board = &replay.puzzle;
+								let text = format!("{} {}x{}{} - {:.2}s", replay.mode, board.width, board.height, board.rules.label(), replay.duration());
+								if button(&Rect { x: 0.0, y, w: 1.0, h: 0.1 }, PRI_BUTTON_COL, text.as_str(), &camera, font, 0.05) && handle_mouse {
+									ret = Some(State::Replay(replay.clone(), 0.0, 1.0, true, replay.verify()));
+									assets.play_sound(FORWARD);
+								}
+							}
+							Err(e) => {
+								draw_round_rect(0.0, y, 1.0, 0.1, 0.01, SEC_BUTTON_COL);
+								let e: String = e.chars().take(60).collect();
+								draw_centered_text_color(vec2(0.5, y + 0.05), e.as_str(), font, 0.03, GRAY);
+							}
+						}
+					}
+					y += 0.12;
+				}
+			}
+			Self::Replay(replay, position, speed, playing, verified) => {
+				let mut board = replay.board_at(*position);
+				let board_h = board.rect().h;
+				let display_rect = rect_circumscribed_on_rect(Rect { x: -0.1, y: -0.2, w: 1.2, h: board_h + 0.45 }, screen_width()/screen_height());
+				let camera = Camera2D::from_display_rect(display_rect);
+				set_camera(&camera);
+
+				let duration = replay.duration();
+				if *playing {
+					*position = (*position + get_frame_time() * *speed).min(duration);
+					if *position >= duration {
+						*playing = false;
+					}
+				}
+
+				board.draw_hint();
+				board.draw(assets);
+				draw_centered_text(vec2(0.3, -0.1), format!("{:.2}s / {:.2}s", position, duration).as_str(), font, 0.07);
+
+				let y = board_h + 0.05;
+				if button(&Rect { x: 0.0, y, w: 0.2, h: 0.1 }, PRI_BUTTON_COL, if *playing {"Pause"} else {"Play"}, &camera, font, 0.05) && handle_mouse {
+					assets.play_sound(FORWARD);
+					if *position >= duration {
+						*position = 0.0;
+					}
+					*playing = !*playing;
+				}
+				if button(&Rect { x: 0.22, y, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, format!("{speed}x").as_str(), &camera, font, 0.05) && handle_mouse {
+					assets.play_sound(FORWARD);
+					*speed = if *speed >= 8.0 {0.5} else {*speed * 2.0};
+				}
+				// dragging the slider scrubs through the game
+				slider(position, 0.0, duration.max(0.01), vec2(0.5, y + 0.05), 0.5, SLIDER_COL, &camera);
+
+				match verified {
+					Ok(()) => draw_centered_text_color(vec2(0.5, y + 0.2), "the moves lead to the recorded win", font, 0.04, GREEN),
+					Err(e) => draw_centered_text_color(vec2(0.5, y + 0.2), e.as_str(), font, 0.04, RED),
+				}
+
+				if button(&Rect { x: 0.8, y: -0.15, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Back", &camera, font, 0.06) && handle_mouse {
+					assets.play_sound(BACKWARD);
+					ret = Some(State::Replays(Assets::load_replays(), 0.0));
+				}
+				// a new serious game on the same puzzle, against the best run on it
+				if replay.mode == "serious" && button(&Rect { x: 0.57, y: -0.15, w: 0.2, h: 0.1 }, PRI_BUTTON_COL, "Race", &camera, font, 0.06) && handle_mouse {
+					assets.play_sound(FORWARD);
+					let mut board = replay.puzzle.clone();
+					board.id = assets.next_board_id;
+					assets.next_board_id += 1;
+					let strict = assets.persistance.strict.then(|| Strict::new(&board));
+					let ghost = Assets::best_replay(&board).map(Box::new);
+					let replay = recording("serious", &board);
+					ret = Some(State::Serious(SeriousGame::new(board, strict, replay, ghost)));
+				}
+			}
+			Self::Versus(players, start_time, sounds, winner) => {
+				let generating = players[0].board.is_generating;
+				let running = get_time() as f32 > *start_time && !generating && winner.is_none();
+				let passed = match winner {
+					Some((_, time)) => *time,
+					None => get_time() as f32 - *start_time,
+				};
+
+				// the screen is split in two, a camera for each half
+				let half = screen_width() / 2.0;
+				for (i, player) in players.iter_mut().enumerate() {
+					let board_h = player.board.rect().h;
+					let mut camera = Camera2D::from_display_rect(rect_circumscribed_on_rect(Rect { x: -0.1, y: -0.3, w: 1.2, h: board_h + 0.5 }, half/screen_height()));
+					camera.viewport = Some(((i as f32 * half) as i32, 0, half as i32, screen_height() as i32));
+					set_camera(&camera);
+
+					if running && handle_mouse {
+						player.handle_keys(&CONTROLS[i], assets);
+					}
+					player.board.draw(assets);
+					if !generating {
+						player.draw_cursor();
+					}
+
+					let col = if winner.is_some_and(|(w, _)| w == i) {GREEN} else {WHITE};
+					draw_centered_text_color(vec2(0.5, -0.2), format!("Player {}", i + 1).as_str(), font, 0.08, col);
+					if passed > 0.0 && !generating {
+						draw_centered_text(vec2(0.5, -0.09), format!("{passed:.2}s").as_str(), font, 0.07);
+					}
+					draw_centered_text_color(vec2(0.5, board_h + 0.07), CONTROLS[i].help, font, 0.04, GRAY);
+					if !player.board.has_nones() && !player.board.is_valid {
+						draw_centered_text_color(vec2(0.5, board_h + 0.13), "something is wrong", font, 0.04, RED);
+					}
+				}
+
+				// the first board solved wins, the other player's board stays as it was
+				if running {
+					if let Some(i) = players.iter().position(|p| p.board.is_won) {
+						let board = &players[i].board;
+						assets.persistance.record_match(board.width, board.height, board.rules, i, passed);
+						*winner = Some((i, passed));
+						ret = Some(State::EndScreen(Box::new(State::Versus(players.clone(), *start_time, *sounds, *winner)), None));
+					}
+				}
+
+				let display_rect = rect_circumscribed_on_rect(Rect { x: 0.0, y: 0.0, w: 1.0, h: 1.0 }, screen_width()/screen_height());
+				let camera = Camera2D::from_display_rect(display_rect);
+				set_camera(&camera);
+				draw_line(0.5, display_rect.top(), 0.5, display_rect.bottom(), 0.005, POPUP_EDGE_COL);
+
+				if button(&Rect { x: display_rect.right() - 0.14, y: display_rect.top() + 0.02, w: 0.12, h: 0.06 }, SEC_BUTTON_COL, "Exit", &camera, font, 0.04) && handle_mouse {
+					assets.play_sound(BACKWARD);
+					ret = Some(if winner.is_none() {State::ExitConfirmation(Box::new(State::Versus(players.clone(), *start_time, *sounds, None)))} else {State::MainMenu});
+				}
+
+				// the same countdown as in serious games, over both halves
+				if *start_time > get_time() as f32 && !generating {
+					draw_countdown(display_rect, *start_time, sounds, assets);
+				}
+			}
+			Self::TimeAttack(game) => {
+				let board_h = game.board.rect().h;
+				let display_rect = rect_circumscribed_on_rect(Rect { x: -0.1, y: -0.2, w: 1.2, h: board_h + 0.3 }, screen_width()/screen_height());
+				let camera = Camera2D::from_display_rect(display_rect);
+				set_camera(&camera);
+
+				// the clock starts with the first board, waiting for later ones counts
+				let first = game.board.is_generating && game.solved == 0;
+				let left = if first {TIME_ATTACK} else {(TIME_ATTACK - (get_time() as f32 - game.start_time)).clamp(0.0, TIME_ATTACK)};
+				let running = !first && get_time() as f32 > game.start_time && left > 0.0;
+
+				if running && handle_mouse {
+					game.board.handle_mouse(&camera, assets);
+				}
+				game.board.draw(assets);
+
+				if game.board.is_won {
+					game.solved += 1;
+					assets.play_sound(FORWARD);
+					game.board = match game.queue.pop_front() {
+						Some(next) => next,
+						// the generator hands out boards in order, so the next one is the oldest ordered
+						None => Board::new(game.board.width, game.board.height, game.board.rules, game.pending[0], true),
+					};
+					game.pending.push(order_new_board(game.board.width, game.board.height, game.board.rules, GameMode::TimeAttack, assets));
+				}
+
+				draw_centered_text(vec2(0.15, -0.1), format!("{}:{:02}", left.ceil() as u32 / 60, left.ceil() as u32 % 60).as_str(), font, 0.1);
+				draw_centered_text(vec2(0.5, -0.1), format!("solved: {}", game.solved).as_str(), font, 0.06);
+				draw_centered_text_color(vec2(0.5, board_h + 0.07), format!("{} more boards ready", game.queue.len()).as_str(), font, 0.035, GRAY);
+
+				let this = State::TimeAttack(game.clone());
+				if button(&Rect { x: 0.8, y: -0.15, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Exit", &camera, font, 0.06) && handle_mouse {
+					assets.play_sound(BACKWARD);
+					ret = Some(State::ExitConfirmation(Box::new(this)));
+				}
+				else if left <= 0.0 && handle_mouse {
+					let (is_best, previous) = assets.persistance.insert_time_attack(game.board.width, game.board.height, game.board.rules, game.solved);
+					ret = Some(State::EndScreen(Box::new(this), is_best.then_some((game.solved as f32, previous.map(|p| p as f32)))));
+				}
+
+				if game.start_time > get_time() as f32 && !first {
+					draw_countdown(display_rect, game.start_time, &mut game.sounds, assets);
+				}
+			}
+			Self::Zen(game) => {
+				let board_h = game.board.rect().h;
+				let display_rect = rect_circumscribed_on_rect(Rect { x: -0.1, y: -0.2, w: 1.2, h: board_h + 0.3 }, screen_width()/screen_height());
+				let camera = Camera2D::from_display_rect(display_rect);
+				set_camera(&camera);
+
+				let before = game.board.map.clone();
+				if handle_mouse && game.solved_at.is_none() {
+					game.board.handle_mouse(&camera, assets);
+				}
+				if game.board.map != before || game.board.is_generating {
+					game.last_move = get_time() as f32;
+				}
+				if assets.persistance.auto_hints && game.board.hint.is_none() && !game.board.is_won && get_time() as f32 - game.last_move > ZEN_HINT_DELAY {
+					game.board.generate_hint(assets);
+					game.last_move = get_time() as f32;
+				}
+				// errors are shown, but quietly
+				game.board.draw_errors(None);
+				game.board.draw_hint();
+				game.board.draw(assets);
+
+				if game.board.is_won && game.solved_at.is_none() {
+					game.solved += 1;
+					game.solved_at = Some(get_time() as f32);
+					assets.persistance.zen_solved += 1;
+					assets.persistance.save();
+					assets.change_material();
+					assets.play_sound(FORWARD);
+				}
+				// the solved board stays for a moment, then the one generated meanwhile takes its place
+				if game.solved_at.is_some_and(|t| get_time() as f32 - t > ZEN_ADVANCE) {
+					if let Some(board_ahead) = game.next.take() {
+						game.board = board_ahead;
+						game.next_id = order_new_board(game.board.width, game.board.height, game.board.rules, GameMode::Zen, assets);
+						game.solved_at = None;
+						game.last_move = get_time() as f32;
+					}
+				}
+
+				draw_centered_text(vec2(0.5, board_h + 0.07), format!("solved: {}", game.solved).as_str(), font, 0.06);
+				draw_centered_text_color(vec2(0.5, board_h + 0.13), format!("{} in all", assets.persistance.zen_solved).as_str(), font, 0.035, GRAY);
+
+				if button(&Rect { x: 0.0, y: -0.15, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Hint", &camera, font, 0.06) && handle_mouse && game.solved_at.is_none() {
+					game.board.generate_hint(assets);
+					game.last_move = get_time() as f32;
+				}
+				let auto_col = if assets.persistance.auto_hints {PRI_BUTTON_COL} else {SEC_BUTTON_COL};
+				if button(&Rect { x: 0.22, y: -0.15, w: 0.33, h: 0.1 }, auto_col, "Auto hints", &camera, font, 0.05) && handle_mouse {
+					assets.persistance.auto_hints = !assets.persistance.auto_hints;
+					assets.persistance.save();
+					assets.play_sound(FORWARD);
+				}
+				if button(&Rect { x: 0.8, y: -0.15, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Exit", &camera, font, 0.06) && handle_mouse {
+					assets.play_sound(BACKWARD);
+					ret = Some(State::ExitConfirmation(Box::new(State::Zen(game.clone()))));
+				}
+			}
+			Self::Lobby(address, error, mode) => {
+				let display_rect = rect_circumscribed_on_rect(Rect { x: -0.1, y: -0.2, w: 1.2, h: 1.3 }, screen_width()/screen_height());
+				let camera = Camera2D::from_display_rect(display_rect);
+				set_camera(&camera);
+
+				if button(&Rect { x: 0.8, y: -0.1, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Back", &camera, font, 0.06) {
+					assets.play_sound(BACKWARD);
+					ret = Some(State::MainMenu);
+				}
+				draw_centered_text(vec2(0.35, -0.05), "Online", font, 0.08);
+
+				while let Some(c) = get_char_pressed() {
+					if (c.is_ascii_alphanumeric() || ".:-[]".contains(c)) && address.len() < 40 {
+						address.push(c);
+					}
+				}
+				if is_key_pressed(KeyCode::Backspace) {
+					address.pop();
+				}
+				draw_centered_text(vec2(0.5, 0.2), "server address:", font, 0.06);
+				draw_round_rect(0.1, 0.25, 0.8, 0.1, 0.01, POPUP_EDGE_COL);
+				let cursor = if get_time().fract() < 0.5 {"_"} else {" "};
+				draw_centered_text(vec2(0.5, 0.3), format!("{address}{cursor}").as_str(), font, 0.06);
+
+				let host = button(&Rect { x: 0.1, y: 0.45, w: 0.38, h: 0.1 }, PRI_BUTTON_COL, "Host", &camera, font, 0.07) && handle_mouse;
+				let join = button(&Rect { x: 0.52, y: 0.45, w: 0.38, h: 0.1 }, PRI_BUTTON_COL, "Join", &camera, font, 0.07) && handle_mouse;
+				let toggle = match mode {
+					Mode::Race => "Hosting: race",
+					Mode::Coop => "Hosting: co-op",
+				};
+				if button(&Rect { x: 0.1, y: 0.58, w: 0.38, h: 0.08 }, SEC_BUTTON_COL, toggle, &camera, font, 0.045) && handle_mouse {
+					*mode = if *mode == Mode::Race {Mode::Coop} else {Mode::Race};
+					assets.play_sound(POP);
+				}
+				let p = &assets.persistance;
+				let text = match mode {
+					Mode::Race => "two players race on the same puzzle",
+					Mode::Coop => "up to four players fill in one board",
+				};
+				draw_centered_text_color(vec2(0.7, 0.62), text, font, 0.03, GRAY);
+				draw_centered_text_color(vec2(0.5, 0.72), format!("hosted on {}x{}{} boards from your last game, on the port of the address", p.game_width, p.game_height, p.game_rules.label()).as_str(), font, 0.03, GRAY);
+				draw_centered_text_color(vec2(0.5, 0.8), error.as_str(), font, 0.04, RED);
+
+				if host || join {
+					let connected = if host {
+						// other players join on the address of this computer, the host itself connects locally
+						let port = address.rsplit_once(':').and_then(|(_, port)| port.parse().ok()).unwrap_or(PORT);
+						Server::start(&format!("0.0.0.0:{port}"), *mode, p.game_width, p.game_height, p.game_rules)
+							.and_then(|server| Client::connect(&format!("127.0.0.1:{}", server.address().port()), &player_name()).map(|client| Online::new(client, Some(server))))
+					}
+					else {
+						Client::connect(address, &player_name()).map(|client| Online::new(client, None))
+					};
+					match connected {
+						Ok(online) => {
+							assets.persistance.address = address.clone();
+							assets.persistance.save();
+							assets.play_sound(FORWARD);
+							ret = Some(State::Online(Box::new(online)));
+						}
+						Err(e) => {
+							*error = e;
+							assets.play_sound(ERROR);
+						}
+					}
+				}
+			}
+			Self::Online(online) => {
+				while let Some(message) = online.client.try_recv() {
+					online.handle(message, assets);
+				}
+
+				let board_h = online.board.as_ref().map(|board| board.rect().h).unwrap_or(1.0);
+				let display_rect = rect_circumscribed_on_rect(Rect { x: -0.1, y: -0.2, w: 1.2, h: board_h + 0.4 }, screen_width()/screen_height());
+				let camera = Camera2D::from_display_rect(display_rect);
+				set_camera(&camera);
+
+				let leave = button(&Rect { x: 0.8, y: -0.15, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Leave", &camera, font, 0.06) && handle_mouse;
+				if leave {
+					assets.play_sound(BACKWARD);
+					ret = Some(State::Lobby(assets.persistance.address.clone(), String::new(), online.mode));
+				}
+				let opponent = online.opponent.clone().unwrap_or("opponent".to_string());
+
+				match &mut online.board {
+					None => {
+						let waiting = if online.mode == Mode::Race {"waiting for an opponent"} else {"joining the game"};
+						let text = match &online.server {
+							Some(server) => format!("{waiting} on port {}", server.address().port()),
+							None => waiting.to_string(),
+						};
+						draw_centered_text(vec2(0.5, 0.4), text.as_str(), font, 0.05);
+						draw_centered_text_color(vec2(0.5, 0.5), online.status.as_str(), font, 0.04, RED);
+					}
+					Some(board) => {
+						let running = get_time() as f32 > online.start_time && online.result.is_none();
+						let passed = match online.result {
+							Some((_, time)) => time,
+							None => (get_time() as f32 - online.start_time).max(0.0),
+						};
+						draw_centered_text(vec2(0.3, -0.1), format!("{passed:.2}s").as_str(), font, 0.1);
+
+						let before = board.map.clone();
+						if running && handle_mouse && !leave {
+							board.handle_mouse(&camera, assets);
+						}
+						board.draw(assets);
+						if board.map != before {
+							// a failed send shows up as the connection closing
+							match online.mode {
+								Mode::Race => {
+									let _ = online.client.send(&Message::Progress(Replay::filled(&board.map)));
+									if board.is_won {
+										let _ = online.client.send(&Message::Finish(board.clone()));
+									}
+								}
+								// shown right away, the server sends the cell back if someone else got there first
+								Mode::Coop => for (y, x) in (0..board.height).flat_map(|y| (0..board.width).map(move |x| (y, x))) {
+									if board.map[y][x] != before[y][x] {
+										let _ = online.client.send(&Message::Set(x, y, board.map[y][x], online.versions[y][x]));
+										online.versions[y][x] += 1;
+										online.owners[y][x] = board.map[y][x].is_filled().then_some(online.index);
+									}
+								}
+							}
+						}
+
+						let total = board.map.iter().flatten().filter(|cell| !cell.is_locked() && **cell != CellState::Blocked).count().max(1);
+						match online.mode {
+							// how much of the board each player filled in
+							Mode::Race => {
+								let bars = [("you", Replay::filled(&board.map)), (opponent.as_str(), online.opponent_progress)];
+								for (i, (name, filled)) in bars.into_iter().enumerate() {
+									let y = board_h + 0.05 + i as f32 * 0.06;
+									draw_centered_text_color(vec2(0.1, y + 0.015), name, font, 0.035, WHITE);
+									draw_round_rect(0.22, y, 0.78, 0.03, 0.01, POPUP_EDGE_COL);
+									draw_round_rect(0.22, y, 0.78 * (filled as f32 / total as f32).min(1.0), 0.03, 0.01, if i == 0 {WHITE} else {ORANGE});
+								}
+							}
+							// a dot in the corner of every cell in its owner's color, and the cells everyone has
+							Mode::Coop => {
+								let w = board.cell_size();
+								let origin = board.rect().point();
+								for (y, row) in online.owners.iter().enumerate() {
+									for (x, owner) in row.iter().enumerate() {
+										if let Some(owner) = owner {
+											draw_circle(origin.x + (x as f32 + 0.8) * w, origin.y + (y as f32 + 0.8) * w, 0.08 * w, OWNER_COLS[owner % OWNER_COLS.len()]);
+										}
+									}
+								}
+
+								let players = std::iter::once((online.index, "you")).chain(online.players.iter().map(|(id, name)| (*id, name.as_str())));
+								for (i, (id, name)) in players.enumerate() {
+									let x = (i % 2) as f32 * 0.5;
+									let y = board_h + 0.06 + (i / 2) as f32 * 0.05;
+									let owned = online.owners.iter().flatten().filter(|owner| **owner == Some(id)).count();
+									draw_circle(x + 0.03, y, 0.015, OWNER_COLS[id % OWNER_COLS.len()]);
+									draw_centered_text_color(vec2(x + 0.25, y), format!("{name}: {owned}/{total}").as_str(), font, 0.035, WHITE);
+								}
+							}
+						}
+						draw_centered_text_color(vec2(0.5, board_h + 0.22), online.status.as_str(), font, 0.04, RED);
+
+						if let Some((winner, time)) = online.result {
+							draw_rectangle(display_rect.x, display_rect.y, display_rect.w, display_rect.h, Color { r: 0.0, g: 0.0, b: 0.0, a: 0.6 });
+							let title = match online.mode {
+								Mode::Coop => "Solved together!".to_string(),
+								Mode::Race if winner == online.index => "You win!".to_string(),
+								Mode::Race => format!("{opponent} wins"),
+							};
+							draw_centered_text(vec2(0.5, 0.3), title.as_str(), font, 0.1);
+							draw_centered_text(vec2(0.5, 0.42), format!("{time:.2}s").as_str(), font, 0.07);
+
+							let others = if online.mode == Mode::Race {opponent.clone()} else {"the others".to_string()};
+							let text = match online.rematch {
+								(true, _) => format!("waiting for {others}"),
+								(false, true) if online.mode == Mode::Race => format!("{opponent} wants a rematch"),
+								(false, true) => "someone wants another round".to_string(),
+								(false, false) => String::new(),
+							};
+							draw_centered_text_color(vec2(0.5, 0.52), text.as_str(), font, 0.04, GRAY);
+							if !online.rematch.0 && button(&Rect { x: 0.3, y: 0.6, w: 0.4, h: 0.1 }, PRI_BUTTON_COL, "Rematch", &camera, font, 0.07) && handle_mouse {
+								assets.play_sound(FORWARD);
+								online.rematch.0 = true;
+								let _ = online.client.send(&Message::Rematch);
+							}
+						}
+						else if online.start_time > get_time() as f32 {
+							draw_countdown(display_rect, online.start_time, &mut online.sounds, assets);
+						}
+					}
+				}
+			}
+			Self::Packs(packs, scroll) => {
+				let display_rect = rect_circumscribed_on_rect(Rect { x: -0.1, y: -0.2, w: 1.2, h: 1.3 }, screen_width()/screen_height());
+				let camera = Camera2D::from_display_rect(display_rect);
+				set_camera(&camera);
+
+				if button(&Rect { x: 0.8, y: -0.1, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Back", &camera, font, 0.06) {
+					assets.play_sound(BACKWARD);
+					ret = Some(State::MainMenu);
+				}
+				draw_centered_text(vec2(0.35, -0.05), "Puzzle packs", font, 0.08);
+
+				let max_scroll = (packs.len() as f32 * 0.12 - 1.0).max(0.0);
+				*scroll = (*scroll - mouse_wheel().1 * 0.05).clamp(0.0, max_scroll);
+
+				let mut y = 0.05 - *scroll;
+				for pack in packs.iter() {
+					if y > 0.0 && y < 1.0 {
+						match pack {
+							Ok(pack) => {
+								let by = if pack.author.is_empty() {String::new()} else {format!(" by {}", pack.author)};
+								let text = format!("{}{by} ({})", pack.title, pack.puzzles.len());
+								if button(&Rect { x: 0.0, y, w: 1.0, h: 0.1 }, PRI_BUTTON_COL, text.as_str(), &camera, font, 0.05) && handle_mouse {
+									ret = Some(State::PackPuzzles(pack.clone(), 0.0));
+									assets.play_sound(FORWARD);
+								}
+							}
+							Err(e) => {
+								draw_round_rect(0.0, y, 1.0, 0.1, 0.01, SEC_BUTTON_COL);
+								let e: String = e.chars().take(60).collect();
+								draw_centered_text_color(vec2(0.5, y + 0.05), e.as_str(), font, 0.03, GRAY);
+							}
+						}
+					}
+					y += 0.12;
+				}
+			}
+			Self::PackPuzzles(pack, scroll) => {
+				let display_rect = rect_circumscribed_on_rect(Rect { x: -0.1, y: -0.2, w: 1.2, h: 1.3 }, screen_width()/screen_height());
+				let camera = Camera2D::from_display_rect(display_rect);
+				set_camera(&camera);
+
+				if button(&Rect { x: 0.8, y: -0.1, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Back", &camera, font, 0.06) {
+					assets.play_sound(BACKWARD);
+					ret = Some(State::Packs(Assets::load_packs(), 0.0));
+				}
+				draw_centered_text(vec2(0.35, -0.05), pack.title.as_str(), font, 0.08);
+
+				let max_scroll = (pack.puzzles.len() as f32 * 0.12 - 1.0).max(0.0);
+				*scroll = (*scroll - mouse_wheel().1 * 0.05).clamp(0.0, max_scroll);
+
+				let mut y = 0.05 - *scroll;
+				for puzzle in pack.puzzles.iter() {
+					if y > 0.0 && y < 1.0 {
+						let board = &puzzle.board;
+						draw_centered_text(vec2(0.27, y + 0.035), puzzle.name.as_str(), font, 0.05);
+						draw_centered_text_color(vec2(0.27, y + 0.085), format!("{}x{}{} {}", board.width, board.height, board.rules.label(), puzzle.difficulty.as_str()).as_str(), font, 0.035, GRAY);
+
+						let mut play = None;
+						if button(&Rect { x: 0.58, y, w: 0.2, h: 0.1 }, PRI_BUTTON_COL, "Learn", &camera, font, 0.05) && handle_mouse {
+							play = Some(GameMode::Learn);
+						}
+						if button(&Rect { x: 0.8, y, w: 0.2, h: 0.1 }, PRI_BUTTON_COL, "Serious", &camera, font, 0.05) && handle_mouse {
+							play = Some(GameMode::Serious);
+						}
+
+						if let Some(mode) = play {
+							let mut board = board.clone();
+							board.id = assets.next_board_id;
+							assets.next_board_id += 1;
+							ret = Some(match mode {
+								GameMode::Serious => {
+									let strict = assets.persistance.strict.then(|| Strict::new(&board));
+									let replay = recording("serious", &board);
+									let ghost = Assets::best_replay(&board).map(Box::new);
+									State::Serious(SeriousGame::new(board, strict, replay, ghost))
+								}
+								_ => {
+									let replay = recording("learn", &board);
+									State::Learn(board, replay)
+								}
+							});
+							assets.play_sound(FORWARD);
+						}
+					}
+					y += 0.12;
+				}
+			}
+			Self::Campaign(scroll) => {
+				let display_rect = rect_circumscribed_on_rect(Rect { x: -0.1, y: -0.2, w: 1.2, h: 1.3 }, screen_width()/screen_height());
+				let camera = Camera2D::from_display_rect(display_rect);
+				set_camera(&camera);
+
+				if button(&Rect { x: 0.8, y: -0.1, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Back", &camera, font, 0.06) {
+					assets.play_sound(BACKWARD);
+					ret = Some(State::MainMenu);
+				}
+				let stars: usize = assets.persistance.campaign.iter().map(|p| p.stars().iter().filter(|s| **s).count()).sum();
+				draw_centered_text(vec2(0.35, -0.05), format!("Campaign {stars}/{}", assets.campaign.len() * 3).as_str(), font, 0.07);
+
+				let rows = assets.campaign.len().div_ceil(5);
+				let max_scroll = (rows as f32 * 0.22 - 1.0).max(0.0);
+				*scroll = (*scroll - mouse_wheel().1 * 0.05).clamp(0.0, max_scroll);
+
+				// 5 levels per row, each with its number and 3 stars below
+				for (level, puzzle) in assets.campaign.iter().enumerate() {
+					let x = (level % 5) as f32 * 0.2 + 0.015;
+					let y = (level / 5) as f32 * 0.22 + 0.02 - *scroll;
+					if !(0.0..=1.0).contains(&y) {
+						continue;
+					}
+
+					let rect = Rect { x, y, w: 0.17, h: 0.12 };
+					if assets.persistance.is_unlocked(level) {
+						if button(&rect, PRI_BUTTON_COL, format!("{}", level + 1).as_str(), &camera, font, 0.07) && handle_mouse {
+							let mut board = puzzle.board.clone();
+							board.id = assets.next_board_id;
+							assets.next_board_id += 1;
+							ret = Some(State::CampaignLevel(board, level, get_time() as f32, None, 0, 0));
+							assets.play_sound(FORWARD);
+						}
+					}
+					else {
+						draw_round_rect(rect.x, rect.y, rect.w, rect.h, 0.01, SEC_BUTTON_COL);
+						let s = 0.07;
+						draw_texture_ex(assets.lock, rect.center().x - s/2.0, rect.center().y - s/2.0, WHITE, DrawTextureParams { dest_size: Some(vec2(s, s)), ..Default::default() });
+					}
+
+					let stars = assets.persistance.campaign.get(level).map(|p| p.stars()).unwrap_or_default();
+					for (i, star) in stars.iter().enumerate() {
+						let center = vec2(x + 0.035 + i as f32 * 0.05, y + 0.155);
+						draw_circle(center.x, center.y, 0.018, if *star {ORANGE} else {POPUP_EDGE_COL});
+					}
+				}
+			}
+			Self::CampaignLevel(board, level, start_time, finished_time, hints, errors) => {
+				let display_rect = rect_circumscribed_on_rect(Rect { x: -0.1, y: -0.2, w: 1.2, h: board.rect().h + 0.3 }, screen_width()/screen_height());
+				let camera = Camera2D::from_display_rect(display_rect);
+				set_camera(&camera);
+
+				let status_color = if board.is_won {Some(GREEN)} else if board.is_valid {None} else {Some(RED)};
+
+				if let Some(mut c) = status_color {
+					c.a = board.get_error_alpha();
+					let w = 0.2;
+					draw_texture_ex(assets.gradient, display_rect.left(), display_rect.top(), c, 
+					DrawTextureParams { 
+						source: Some(Rect{x: 0.5, y: 0.0, w: 1.0, h: 1.0}),
+						dest_size: Some(vec2(w, display_rect.h)),
+						pivot: Some(display_rect.center()),
+						rotation: PI,
+						..Default::default()
+					});
+					draw_texture_ex(assets.gradient, display_rect.left(), display_rect.top(), c, 
+					DrawTextureParams { 
+						source: Some(Rect{x: 0.5, y: 0.0, w: 1.0, h: 1.0}),
+						dest_size: Some(vec2(w, display_rect.h)),
+						..Default::default()
+					});
+				}
+
+				if handle_mouse {
+					let was_valid = board.is_valid;
+					board.handle_mouse(&camera, assets);
+					if was_valid && !board.is_valid {
+						*errors += 1;
+					}
+				}
+				board.draw_errors(Some(assets));
+				board.draw_hint();
+				board.draw(assets);
+
+				let passed = if let Some(t) = *finished_time {t} else {get_time() as f32 - *start_time};
+				let mut str = format!("0{:.2}s", passed);
+				if passed >= 10.0 {str = str[1..].to_owned();}
+				for (i, c) in str.chars().enumerate() {
+					draw_centered_text_stable(vec2(i as f32 * 0.07 + 0.04, -0.1), [c].iter().collect::<String>().as_str(), "0", font, 0.1);
+				}
+
+				if handle_mouse {
+					if button(&Rect { x: 0.55, y: -0.15, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Hint", &camera, font, 0.06) {
+						board.generate_hint(assets);
+						if board.hint.is_some() {
+							*hints += 1;
+						}
+					}
+					if button(&Rect { x: 0.8, y: -0.15, w: 0.2, h: 0.1 }, SEC_BUTTON_COL, "Exit", &camera, font, 0.06) {
+						ret = Some(State::ExitConfirmation(Box::new(self.clone())));
+						assets.play_sound(BACKWARD);
+					}
+					else if board.is_won && finished_time.is_none() {
+						let time = get_time() as f32 - *start_time;
+						*finished_time = Some(time);
+						let previous = assets.persistance.complete_level(*level, time, par_time(board), *hints, *errors);
+						let best = match previous {
+							Some(p) if p.best_time <= time => None,
+							_ => Some((time, previous.map(|p| p.best_time))),
+						};
+						ret = Some(State::EndScreen(Box::new(self.clone()), best));
+					}
+				}
+			}
 		}
 		
 		if let Some(Self::MainMenu) = ret {
@@ -664,18 +1954,124 @@ impl State {
 		ret
 	}
 
-	pub fn capture_generated_map(&mut self, map_size: usize, map: Vec<Vec<CellState>>, id: usize, time: f32) {
+	// the board on screen and a caption with the mode and time, for saving images
+	pub fn shown_board(&self) -> Option<(&Board, String)> {
+		let time = |start: f32, finished: Option<f32>| finished.unwrap_or(get_time() as f32 - start).max(0.0);
+		match self {
+			Self::Sandbox(board) => Some((board, "Sandbox".to_string())),
+			Self::Learn(board, _) => Some((board, "Learn".to_string())),
+			Self::Serious(game) => Some((&game.board, format!("Serious - {:.2}s", time(game.start_time, game.finished_time)))),
+			Self::CampaignLevel(board, level, start, finished, _, _) => Some((board, format!("Campaign level {} - {:.2}s", level + 1, time(*start, *finished)))),
+			Self::TimeAttack(TimeAttackGame { board, solved, .. }) => Some((board, format!("Time attack - {solved} boards"))),
+			Self::Zen(ZenGame { board, .. }) => Some((board, "Zen".to_string())),
+			Self::EndScreen(inner, _) | Self::ExitConfirmation(inner) => inner.shown_board(),
+			_ => None,
+		}
+	}
+
+	pub fn save_image(&self, assets: &Assets) {
+		let Some((board, caption)) = self.shown_board() else { return };
+		let caption = if assets.persistance.image_caption {Some(caption.as_str())} else {None};
+		match assets.save_image(board, caption) {
+			Ok(_) => assets.play_sound(FORWARD),
+			Err(e) => {
+				eprintln!("can't save the image: {e}");
+				assets.play_sound(BACKWARD);
+			}
+		}
+	}
+
+	// boards ordered from the generator that are still wanted, the others can be cancelled
+	pub fn ordered_boards(&self) -> Vec<usize> {
+		let generating = |board: &Board| if board.is_generating {vec![board.id]} else {Vec::new()};
+		match self {
+			Self::Learn(board, _) | Self::Serious(SeriousGame { board, .. }) => generating(board),
+			Self::Versus(players, ..) => generating(&players[0].board),
+			Self::TimeAttack(game) => game.pending.clone(),
+			Self::Zen(game) => {
+				let mut ids = generating(&game.board);
+				if game.next.is_none() {
+					ids.push(game.next_id);
+				}
+				ids
+			}
+			Self::Paused(inner, _) | Self::ExitConfirmation(inner) => inner.ordered_boards(),
+			_ => Vec::new(),
+		}
+	}
+
+	pub fn capture_progress(&mut self, id: usize, phase: Phase, done: f32) {
+		let boards: Vec<&mut Board> = match self {
+			Self::Learn(board, _) | Self::Serious(SeriousGame { board, .. }) | Self::TimeAttack(TimeAttackGame { board, .. }) | Self::Zen(ZenGame { board, .. }) => vec![board],
+			Self::Versus(players, ..) => players.iter_mut().map(|player| &mut player.board).collect(),
+			Self::ExitConfirmation(inner) => return inner.capture_progress(id, phase, done),
+			_ => return,
+		};
+		for board in boards {
+			if board.id == id && board.is_generating {
+				board.progress = Some((phase, done));
+			}
+		}
+	}
+
+	pub fn capture_generated_board(&mut self, generated: Board, id: usize, time: f32) {
 		match self {
-			Self::Learn(board) => {
-				if board.id != id || board.size != map_size || !board.is_generating { return; }
-				board.map = map;
+			Self::Learn(board, replay) => {
+				if board.id != id || !board.is_generating { return; }
+				board.map = generated.map;
+				board.constraints = generated.constraints;
+				*replay = recording("learn", board);
 				board.is_generating = false;
 				board.generation_end_time = get_time() as f32;
 				board.generation_duration = time;
 			}
-			Self::Serious(board, start, _, _) => {
-				if board.id != id || board.size != map_size || !board.is_generating { return; }
-				board.map = map;
+			Self::Versus(players, start, _, _) => {
+				if players[0].board.id != id || !players[0].board.is_generating { return; }
+				for player in players.iter_mut() {
+					let board = &mut player.board;
+					board.map = generated.map.clone();
+					board.constraints = generated.constraints.clone();
+					board.is_generating = false;
+					board.generation_end_time = get_time() as f32;
+					board.generation_duration = time;
+				}
+				*start = get_time() as f32 + 1.5;
+			}
+			Self::TimeAttack(TimeAttackGame { board, start_time: start, solved, queue, pending, .. }) => {
+				let Some(i) = pending.iter().position(|p| *p == id) else { return };
+				pending.remove(i);
+				if board.id == id && board.is_generating {
+					board.fill_generated(generated, get_time() as f32, time);
+					if *solved == 0 {
+						*start = get_time() as f32 + 1.5;
+					}
+				}
+				else {
+					let mut ready = Board::new(board.width, board.height, board.rules, id, true);
+					ready.fill_generated(generated, get_time() as f32, time);
+					queue.push_back(ready);
+				}
+			}
+			Self::Zen(ZenGame { board, next, next_id, .. }) => {
+				if board.id == id && board.is_generating {
+					board.fill_generated(generated, get_time() as f32, time);
+				}
+				else if *next_id == id {
+					let mut ready = Board::new(board.width, board.height, board.rules, id, true);
+					ready.fill_generated(generated, get_time() as f32, time);
+					*next = Some(ready);
+				}
+			}
+			// boards ordered ahead keep coming in while the exit confirmation is up
+			Self::ExitConfirmation(inner) => inner.capture_generated_board(generated, id, time),
+			Self::Serious(SeriousGame { board, start_time: start, strict, replay, .. }) => {
+				if board.id != id || !board.is_generating { return; }
+				if let Some(strict) = strict {
+					strict.solution = generated.unique_solution().map(Box::new);
+				}
+				board.map = generated.map;
+				board.constraints = generated.constraints;
+				*replay = recording("serious", board);
 				board.is_generating = false;
 				*start = get_time() as f32 + 1.5;
 				board.generation_end_time = get_time() as f32;
@@ -684,4 +2080,61 @@ impl State {
 			_ => {}
 		}
 	}
-}
\ No newline at end of file
+}
+
+// the 1.5s before a race, darkening the rect
+fn draw_countdown(display_rect: Rect, start_time: f32, sounds: &mut usize, assets: &Assets) {
+	let font = assets.font;
+	let countdown = ((start_time - get_time() as f32) / 1.5 * 4.0).floor();
+	let t = 1.0 - ((start_time - get_time() as f32) / 1.5 * 4.0).fract();
+	draw_rectangle(display_rect.x, display_rect.y, display_rect.w, display_rect.h, Color { r: 0.0, g: 0.0, b: 0.0, a: 0.8 });
+	let col = Color {r: 1.0, g: 1.0, b: 1.0, a: (t*t*3.0).min(1.0)};
+	if countdown > 0.0 {
+		draw_centered_text_color(display_rect.center(), format!("{countdown}").as_str(), font, 0.4-t*0.2, col);
+	}
+	if countdown < 3.0 {
+		draw_centered_text(display_rect.center(), format!("{}", countdown+1.0).as_str(), font, 0.2-t*0.2);
+	}
+
+	if countdown > 0.0 && 3.0 - (*sounds as f32) >= countdown {
+		*sounds += 1;
+		assets.play_sound(TICK);
+	}
+}
+
+// the last frame took so long the window must have been minimized or hidden, as far as can be told without focus events
+fn window_was_hidden() -> bool {
+	get_frame_time() > HIDDEN_STALL
+}
+
+// asks the generator thread for a board, returns its id
+fn order_new_board(width: usize, height: usize, rules: Rules, mode: GameMode, assets: &mut Assets) -> usize {
+	let id = assets.next_board_id;
+	assets.next_board_id += 1;
+	assets.order_board(width, height, rules, mode, id);
+	id
+}
+
+// a new time attack session, waiting for its first board with a few more ordered behind it
+fn time_attack(width: usize, height: usize, rules: Rules, assets: &mut Assets) -> State {
+	let pending: Vec<usize> = (0..=PREFETCHED).map(|_| order_new_board(width, height, rules, GameMode::TimeAttack, assets)).collect();
+	let board = Board::new(width, height, rules, pending[0], true);
+	State::TimeAttack(TimeAttackGame { board, start_time: get_time() as f32 + 1.5, sounds: 0, solved: 0, queue: VecDeque::new(), pending })
+}
+
+// a recording of a game on the board, started now
+fn recording(mode: &str, board: &Board) -> Replay {
+	let mut replay = Replay::new(mode, board);
+	replay.start = get_time() as f32;
+	replay
+}
+
+// takes back the last cell change of the game
+fn undo(board: &mut Board, replay: &mut Replay, time: f32) {
+	if let Some((x, y, state)) = replay.undo_target() {
+		board.map[y][x] = state;
+		board.hint = None;
+		board.verify_board_now();
+		replay.record(time, Action::Undo);
+	}
+}
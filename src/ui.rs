@@ -26,8 +26,8 @@ pub fn rect_circumscribed_on_rect(rect: Rect, aspect: f32) -> Rect {
 }
 
 pub fn draw_round_rect(x: f32, y: f32, w: f32, h: f32, r: f32, col: Color) {
-	draw_rectangle(x + r, y as f32, w - 2.0*r, h, col);
-	draw_rectangle(x, y as f32 + r, w, h - 2.0*r, col);
+	draw_rectangle(x + r, y, w - 2.0*r, h, col);
+	draw_rectangle(x, y + r, w, h - 2.0*r, col);
 
 	draw_circle(x + r, 		y + r, 		r, col);
 	draw_circle(x + w - r, 	y + r, 		r, col);
@@ -52,7 +52,7 @@ pub fn button(rect: &Rect, mut col: Color, text: &str, camera: &Camera2D, font:
 
 	draw_centered_text(rect.center(), text, font, scale);
 
-	return is_mouse_button_pressed(MouseButton::Left) && rect.contains(mouse);
+	is_mouse_button_pressed(MouseButton::Left) && rect.contains(mouse)
 }
 
 pub fn slider(val: &mut f32, from: f32, to: f32, left: Vec2, width: f32, col: Color, camera: &Camera2D) {
@@ -82,7 +82,7 @@ pub fn draw_centered_text(center: Vec2, text: &str, font: Font, scale: f32) {
 	let dims = measure_text(text, Some(font), 128, 1.0/128.0 * scale);
 
 	draw_text_ex(text, center.x - dims.width/2.0, center.y + dims.height/2.0, TextParams { 
-	   font: font, 
+	   font, 
 	   font_size: 128,
 	   font_scale: 1.0/128.0 * scale, 
 	   font_scale_aspect: 1.0, 
@@ -95,7 +95,7 @@ pub fn draw_centered_text_color(center: Vec2, text: &str, font: Font, scale: f32
 	let dims = measure_text(text, Some(font), 128, 1.0/128.0 * scale);
 
 	draw_text_ex(text, center.x - dims.width/2.0, center.y + dims.height/2.0, TextParams { 
-	   font: font, 
+	   font, 
 	   font_size: 128,
 	   font_scale: 1.0/128.0 * scale, 
 	   font_scale_aspect: 1.0, 
@@ -108,7 +108,7 @@ pub fn draw_centered_text_stable(center: Vec2, text: &str, format: &str, font: F
 	let dims = measure_text(format, Some(font), 128, 1.0/128.0 * scale);
 
 	draw_text_ex(text, center.x - dims.width/2.0, center.y + dims.height/2.0, TextParams { 
-	   font: font, 
+	   font, 
 	   font_size: 128,
 	   font_scale: 1.0/128.0 * scale, 
 	   font_scale_aspect: 1.0, 